@@ -2,38 +2,111 @@
 pub mod ndc;
 pub mod odata;
 
-/// Translate an EDMX document into the ndc-odata metadata type.
-pub fn prepare_odata_edmx(metadata: odata::EDMX) -> ndc::Schema {
+use std::collections::BTreeSet;
+
+/// Translate an EDMX document into the ndc-odata metadata type. A broken document still produces a
+/// schema: `ndc::validation::validate` says what's wrong with it.
+pub fn prepare_odata_edmx(mut metadata: odata::EDMX) -> ndc::Schema {
+    metadata.resolve_aliases();
+    metadata.resolve_container_extensions();
+    metadata.resolve_type_definitions();
+
+    let mut scalar_types: BTreeSet<ndc::ScalarType> = metadata
+        .data_services
+        .schema
+        .iter()
+        .flat_map(ndc::ScalarType::extract_from)
+        .chain(
+            metadata
+                .data_services
+                .schema
+                .iter()
+                .flat_map(ndc::mutations::scalar_types),
+        )
+        .chain(
+            metadata
+                .data_services
+                .schema
+                .iter()
+                .flat_map(ndc::derived_types::scalar_types),
+        )
+        .chain(
+            metadata
+                .data_services
+                .schema
+                .iter()
+                .flat_map(ndc::open_types::scalar_types),
+        )
+        .collect();
+
+    // The mutations of contained collections are generated from the collections themselves, so we
+    // extract each schema's collections once and share them.
+    let collections: Vec<Vec<ndc::Collection>> = metadata
+        .data_services
+        .schema
+        .iter()
+        .map(|schema| ndc::Collection::extract_from(&metadata, schema))
+        .collect();
+
+    // The operators on spatial types take arguments of types that we might not have seen yet.
+    let geography_object_types = ndc::geography::object_types(&scalar_types);
+    scalar_types.append(&mut ndc::geography::scalar_types(&scalar_types));
+
     ndc::Schema {
-        scalar_types: metadata
-            .data_services
-            .schema
-            .iter()
-            .flat_map(ndc::ScalarType::extract_from)
-            .collect(),
+        version: ndc::Version::from(&metadata.version),
+        scalar_types,
         functions: metadata
             .data_services
             .schema
             .iter()
-            .flat_map(ndc::Function::extract_from)
+            .flat_map(|schema| ndc::Function::extract_from(&metadata, schema))
             .collect(),
         procedures: metadata
             .data_services
             .schema
             .iter()
-            .flat_map(ndc::Procedure::extract_from)
+            .flat_map(|schema| ndc::Procedure::extract_from(&metadata, schema))
+            .chain(
+                metadata
+                    .data_services
+                    .schema
+                    .iter()
+                    .zip(&collections)
+                    .flat_map(|(schema, collections)| {
+                        ndc::mutations::procedures(&metadata, schema, collections)
+                    }),
+            )
             .collect(),
-        collections: metadata
+        collections: collections.concat(),
+        enum_types: metadata
             .data_services
             .schema
             .iter()
-            .flat_map(|schema| ndc::Collection::extract_from(&metadata, schema))
+            .flat_map(ndc::EnumType::extract_from)
             .collect(),
         object_types: metadata
             .data_services
             .schema
             .iter()
             .flat_map(|schema| ndc::ObjectType::extract_from(&metadata, schema))
+            .chain(
+                metadata
+                    .data_services
+                    .schema
+                    .iter()
+                    .zip(&collections)
+                    .flat_map(|(schema, collections)| {
+                        ndc::mutations::object_types(&metadata, schema, collections)
+                    }),
+            )
+            .chain(
+                metadata
+                    .data_services
+                    .schema
+                    .iter()
+                    .flat_map(|schema| ndc::keys::object_types(&metadata, schema)),
+            )
+            .chain(geography_object_types)
             .collect(),
     }
 }
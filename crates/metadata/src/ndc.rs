@@ -1,21 +1,37 @@
 //! The `ndc-odata` metadata structure.
 
 pub mod collections;
+pub mod constraints;
+pub mod derived_types;
+pub mod descriptions;
 pub mod endpoint;
+pub mod enum_types;
 pub mod functions;
+pub mod geography;
+pub mod keys;
+pub mod mutations;
 pub mod object_types;
+pub mod open_types;
 pub mod procedures;
+pub mod read_only;
+pub mod restrictions;
 pub mod schema;
 pub mod types;
+pub mod validation;
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 pub use collections::*;
+pub use constraints::{Bound, Constraints};
 pub use endpoint::*;
+pub use enum_types::*;
 pub use functions::*;
 pub use object_types::*;
 pub use procedures::*;
+pub use read_only::ReadOnly;
+pub use restrictions::*;
 pub use schema::*;
 pub use types::*;
 
@@ -31,6 +47,21 @@ pub struct RawConfiguration {
     // endpoint using a `/$metadata` introspection query.
     pub api_endpoint: String,
 
+    /// A local copy of the metadata document, in XML or JSON, to introspect instead of the
+    /// endpoint's `/$metadata`. This helps where the service guards its metadata differently to
+    /// its data, or where the build can't reach the service at all.
+    #[serde(default)]
+    pub metadata_path: Option<String>,
+
+    /// The metadata document itself, written inline. This takes precedence over `metadata_path`.
+    #[serde(default)]
+    pub metadata_document: Option<String>,
+
     #[serde(default)]
     pub schema: Schema,
+
+    /// The dynamic properties of open entity types that we want to select by name, along with
+    /// their types. The metadata can't tell us about these, so introspection leaves them alone.
+    #[serde(default)]
+    pub dynamic_properties: BTreeMap<String, BTreeMap<String, Type>>,
 }
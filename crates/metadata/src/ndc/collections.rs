@@ -8,14 +8,113 @@ use std::collections::BTreeMap;
 
 /// In the NDC world, we represent collections in a very similar way. The chief difference is that,
 /// at the moment, we don't have a notion of nested types in the NDC spec, which is a bit of a
-/// problem given OData's "expandable relationship" model. We get around this by supporting
-/// relationships to collections using their keys, and thus we invent a foreign key relationship.
+/// problem given OData's "expandable relationship" model. We get around this by exposing each
+/// navigation property binding as a relationship, which we query with `$expand`. Where the
+/// navigation property has referential constraints, we also know the foreign key behind it.
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct Collection {
     pub name: String,
-    pub key: String,
+    pub key: Vec<KeyProperty>,
     pub collection_type: super::QualifiedType,
     pub relationships: BTreeMap<String, String>, // navigation property => collection
+
+    /// The documentation of the entity set (or whatever the collection stands for), falling back
+    /// to that of its entity type.
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The columns that relate this collection to the target of each relationship, for those
+    /// navigation properties with referential constraints.
+    #[serde(default)]
+    pub foreign_keys: BTreeMap<String, BTreeMap<String, String>>, // navigation property => columns
+
+    /// The arguments that the collection takes. Only function-backed and contained collections
+    /// have these.
+    #[serde(default)]
+    pub arguments: BTreeMap<String, super::Type>,
+
+    #[serde(default)]
+    pub argument_descriptions: BTreeMap<String, String>,
+
+    /// If this collection is the result of a function, the name of the function that we invoke
+    /// (with the collection's arguments) in order to query it.
+    #[serde(default)]
+    pub function: Option<String>,
+
+    /// If this collection is contained within another, where we find it.
+    #[serde(default)]
+    pub containment: Option<Containment>,
+
+    /// If this collection holds the entities of a derived type within another collection, the
+    /// type cast with which we find them.
+    #[serde(default)]
+    pub type_cast: Option<TypeCast>,
+
+    /// What the service says we can and can't do with this collection.
+    #[serde(default)]
+    pub restrictions: super::Restrictions,
+}
+
+/// Where a contained collection lives: beneath a navigation property of an entity in the parent
+/// collection, which we address using the key in the given argument. For example, `People_Trips`
+/// lives at `/People('x')/Trips`.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct Containment {
+    pub parent: String,
+    pub navigation: String,
+    pub key_argument: String,
+}
+
+/// The entities of a derived type within a polymorphic collection, which we address by appending
+/// the type's qualified name: `People_Employee` lives at `/People/Trippin.Employee`.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct TypeCast {
+    pub collection: String,
+    pub entity_type: String,
+}
+
+/// One part of a collection's key.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct KeyProperty {
+    /// The name by which key predicates refer to this part of the key.
+    pub name: String,
+
+    /// If this part of the key is a property nested within a complex property, the path to that
+    /// property (e.g. `Address/City`). Otherwise, the part is the top-level property `name`.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// The type of the property, which decides how we write this part of the key as a literal:
+    /// `Edm.Guid` keys, for example, aren't quoted.
+    #[serde(default, rename = "type")]
+    pub r#type: Option<super::QualifiedType>,
+}
+
+impl KeyProperty {
+    /// The path to the property within an entity.
+    pub fn path(&self) -> &str {
+        self.path.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Describe the key of an entity type. An entity type without a key has an empty one, which
+    /// validation reports.
+    pub fn extract_from(metadata: &odata::EDMX, entity_type: &odata::EntityType) -> Vec<Self> {
+        let key = entity_type.key(metadata).unwrap_or_default();
+
+        key.iter()
+            .map(|property_ref| KeyProperty {
+                name: property_ref.key_name().to_string(),
+                path: property_ref
+                    .alias
+                    .as_ref()
+                    .map(|_| property_ref.name.clone()),
+                r#type: entity_type
+                    .property_type(metadata, &property_ref.name)
+                    .as_ref()
+                    .map(super::QualifiedType::from),
+            })
+            .collect()
+    }
 }
 
 impl Collection {
@@ -23,16 +122,22 @@ impl Collection {
     /// now, we just transform one into the other.
     pub fn extract_from(metadata: &odata::EDMX, schema: &schema::Schema) -> Vec<Collection> {
         let mut collections = BTreeMap::new();
+        let mut derived_collections = Vec::new();
 
         for entity_set in &schema.entity_container.entity_sets {
-            // For now, we have that the metadata is valid, and we panic if it doesn't exist.
-            // Eventually, it would be nice to do a validation pass over the OData metadata
-            // /before/ we build the connector metadata, just for the sake of error messages.
-            let key = metadata
-                .entity_type(&entity_set.entity_type)
-                .expect("Collection's entity type doesn't exist.")
-                .key_name(metadata)
-                .to_string();
+            // If the entity type doesn't exist, we still declare the collection, and validation
+            // reports its missing type.
+            let entity_type = metadata.entity_type(&entity_set.entity_type);
+
+            let key = entity_type
+                .as_ref()
+                .map(|entity_type| KeyProperty::extract_from(metadata, entity_type))
+                .unwrap_or_default();
+
+            let foreign_keys = entity_type
+                .as_ref()
+                .map(|entity_type| foreign_keys(metadata, entity_type, entity_set))
+                .unwrap_or_default();
 
             let mut relationships = BTreeMap::new();
             let collection_type = super::QualifiedType {
@@ -40,21 +145,384 @@ impl Collection {
                 name: entity_set.entity_type.name.clone(),
             };
 
-            for relationship in &entity_set.navigation_property_bindings {
-                relationships.insert(relationship.path.clone(), relationship.target.clone());
+            for binding in &entity_set.navigation_property_bindings {
+                let target = binding_target(metadata, schema, binding);
+                relationships.insert(binding.path.clone(), target);
             }
 
-            collections.insert(
-                entity_set.name.clone(),
-                Collection {
-                    name: entity_set.name.clone(),
-                    collection_type,
-                    relationships,
-                    key,
-                },
+            let description = super::descriptions::container_child(
+                metadata,
+                schema,
+                &entity_set.name,
+                &entity_set.annotations,
+            )
+            .or_else(|| super::descriptions::structured_type(metadata, &entity_set.entity_type));
+
+            let restrictions = super::Restrictions::extract_from(metadata, schema, entity_set);
+
+            let mut collection = Collection {
+                name: entity_set.name.clone(),
+                collection_type,
+                relationships,
+                description,
+                foreign_keys,
+                key,
+                arguments: BTreeMap::new(),
+                argument_descriptions: BTreeMap::new(),
+                function: None,
+                containment: None,
+                type_cast: None,
+                restrictions: restrictions.clone(),
+            };
+
+            let mut ancestry = vec![entity_set.entity_type.clone()];
+            contained(
+                metadata,
+                &mut collection,
+                &entity_set.name,
+                &mut ancestry,
+                &mut collections,
             );
+
+            let mut derived = type_casts(metadata, schema, entity_set, &restrictions);
+            derived_collections.append(&mut derived);
+            collections.insert(entity_set.name.clone(), collection);
+        }
+
+        // Unbound functions that return collections of entities are composable: we can push a
+        // whole query down onto the function invocation. So, we expose them as collections that
+        // take the function's parameters as arguments. As with any unbound function, we only
+        // expose those that the entity container imports.
+        for function_import in &schema.entity_container.function_imports {
+            let overloads = metadata.unbound_functions(&function_import.function);
+
+            for function in &overloads {
+                let collection_type = match function.entity_collection_type(metadata) {
+                    Some(collection_type) => collection_type,
+                    None => continue,
+                };
+
+                let entity_type = metadata.entity_type(&collection_type);
+
+                let key = entity_type
+                    .as_ref()
+                    .map(|entity_type| KeyProperty::extract_from(metadata, entity_type))
+                    .unwrap_or_default();
+
+                // The results of the function live in an entity set, and so they share its
+                // relationships. The function import tells us which set that is.
+                let mut relationships = BTreeMap::new();
+                let mut foreign_keys = BTreeMap::new();
+
+                if let Some(entity_set) = function_import
+                    .entity_set
+                    .as_ref()
+                    .and_then(|entity_set| schema.entity_set(entity_set))
+                {
+                    for binding in &entity_set.navigation_property_bindings {
+                        let target = binding_target(metadata, schema, binding);
+                        relationships.insert(binding.path.clone(), target);
+                    }
+
+                    foreign_keys = entity_type
+                        .as_ref()
+                        .map(|entity_type| self::foreign_keys(metadata, entity_type, &entity_set))
+                        .unwrap_or_default();
+                }
+
+                let mut arguments = BTreeMap::new();
+
+                for parameter in &function.parameters {
+                    arguments.insert(
+                        parameter.name.clone(),
+                        super::Type::from_type_data(&parameter.r#type),
+                    );
+                }
+
+                // The function import's name might already belong to an entity set, in which case
+                // we use the function's qualified name instead: `Trippin_GetPeople`.
+                let mut name = super::overload_name(
+                    &function_import.name,
+                    &function.parameters,
+                    overloads.len() > 1,
+                );
+
+                if collections.contains_key(&name) {
+                    name = super::overload_name(
+                        &function_import.function.replace('.', "_"),
+                        &function.parameters,
+                        overloads.len() > 1,
+                    );
+                }
+
+                let description = super::descriptions::container_child(
+                    metadata,
+                    schema,
+                    &function_import.name,
+                    &function_import.annotations,
+                )
+                .or_else(|| {
+                    let target = &function_import.function;
+                    super::descriptions::describe(metadata, target, &function.annotations)
+                });
+
+                let argument_descriptions = super::descriptions::parameters(
+                    metadata,
+                    &function_import.function,
+                    &function.parameters,
+                );
+
+                collections.insert(
+                    name.clone(),
+                    Collection {
+                        name,
+                        collection_type: (&collection_type).into(),
+                        relationships,
+                        description,
+                        foreign_keys,
+                        key,
+                        arguments,
+                        argument_descriptions,
+                        function: Some(function_import.name.clone()),
+                        containment: None,
+                        type_cast: None,
+                        restrictions: super::Restrictions::default(),
+                    },
+                );
+            }
+        }
+
+        // We name the collection of a derived type after the entity set and the type's unqualified
+        // name, such as `People_Employee`. That name might already belong to another collection,
+        // or to a derived type of the same name in another namespace, in which case we qualify the
+        // type's name: `People_Trippin_Employee`.
+        let mut derived_names = BTreeMap::new();
+
+        for derived in &derived_collections {
+            *derived_names.entry(derived.name.clone()).or_insert(0) += 1;
+        }
+
+        for mut derived in derived_collections {
+            if collections.contains_key(&derived.name) || derived_names[&derived.name] > 1 {
+                if let Some(type_cast) = &derived.type_cast {
+                    derived.name = format!(
+                        "{}_{}",
+                        type_cast.collection,
+                        type_cast.entity_type.replace('.', "_")
+                    );
+                }
+            }
+
+            collections.insert(derived.name.clone(), derived);
         }
 
         collections.into_values().collect()
     }
 }
+
+/// Expose the entities of each type derived from an entity set's entity type as a collection of
+/// their own. The derived collection shares the bindings and restrictions of the entity set,
+/// including bindings through a cast to the derived type (or one of its ancestors), such as
+/// `Trippin.Employee/Peers`.
+fn type_casts(
+    metadata: &odata::EDMX,
+    schema: &schema::Schema,
+    entity_set: &odata::EntitySet,
+    restrictions: &super::Restrictions,
+) -> Vec<Collection> {
+    let mut collections = Vec::new();
+
+    for derived_type in metadata.derived_types(&entity_set.entity_type) {
+        let entity_type = match metadata.entity_type(&derived_type) {
+            Some(entity_type) => entity_type,
+            None => continue,
+        };
+
+        let ancestry: Vec<String> = metadata
+            .ancestry(&derived_type)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        // The path of each binding as it applies to the derived type, if it applies at all.
+        let path = |binding_path: &str| match binding_path.rsplit_once('/') {
+            Some((cast, name)) if ancestry.iter().any(|x| x == cast) => Some(name.to_string()),
+            Some(_) => None,
+            None => Some(binding_path.to_string()),
+        };
+
+        let mut relationships = BTreeMap::new();
+
+        for binding in &entity_set.navigation_property_bindings {
+            if let Some(path) = path(&binding.path) {
+                relationships.insert(path, binding_target(metadata, schema, binding));
+            }
+        }
+
+        let foreign_keys = foreign_keys(metadata, &entity_type, entity_set)
+            .into_iter()
+            .filter_map(|(binding_path, columns)| Some((path(&binding_path)?, columns)))
+            .collect();
+
+        collections.push(Collection {
+            name: format!("{}_{}", entity_set.name, derived_type.name),
+            key: KeyProperty::extract_from(metadata, &entity_type),
+            collection_type: (&derived_type).into(),
+            relationships,
+            description: super::descriptions::structured_type(metadata, &derived_type),
+            foreign_keys,
+            arguments: BTreeMap::new(),
+            argument_descriptions: BTreeMap::new(),
+            function: None,
+            containment: None,
+            type_cast: Some(TypeCast {
+                collection: entity_set.name.clone(),
+                entity_type: derived_type.to_string(),
+            }),
+            restrictions: restrictions.clone(),
+        });
+    }
+
+    collections
+}
+
+/// Expose the targets of collection-valued containment navigation properties as collections of
+/// their own. Contained entities have no entity set, so we can only reach them through an entity in
+/// the parent collection, and the contained collection takes that entity's key as an argument (as
+/// well as any arguments the parent needs). Containment can nest, but not cycle: the `ancestry` is
+/// the chain of entity types that we're already within.
+fn contained(
+    metadata: &odata::EDMX,
+    parent: &mut Collection,
+    segment: &str,
+    ancestry: &mut Vec<odata::QualifiedType>,
+    collections: &mut BTreeMap<String, Collection>,
+) {
+    let parent_type = match ancestry.last().cloned() {
+        Some(parent_type) => parent_type,
+        None => return,
+    };
+
+    let entity_type = match metadata.entity_type(&parent_type) {
+        Some(entity_type) => entity_type,
+        None => return,
+    };
+
+    for navigation_property in entity_type.navigation_properties(metadata) {
+        let target_type = navigation_property.r#type.underlying_type().clone();
+
+        if !navigation_property.contains_target
+            || !navigation_property.is_collection()
+            || ancestry.contains(&target_type)
+        {
+            continue;
+        }
+
+        let target = match metadata.entity_type(&target_type) {
+            Some(target) => target,
+            None => continue,
+        };
+
+        let name = format!("{}_{}", parent.name, navigation_property.name);
+        let key_argument = format!("{}_key", segment);
+
+        let mut arguments = parent.arguments.clone();
+        arguments.insert(
+            key_argument.clone(),
+            super::keys::key_type(metadata, &parent_type),
+        );
+
+        let description = super::descriptions::describe(
+            metadata,
+            &format!("{parent_type}/{}", navigation_property.name),
+            &navigation_property.annotations,
+        )
+        .or_else(|| super::descriptions::structured_type(metadata, &target_type));
+
+        let mut collection = Collection {
+            name: name.clone(),
+            key: KeyProperty::extract_from(metadata, &target),
+            collection_type: (&target_type).into(),
+            relationships: BTreeMap::new(),
+            description,
+            foreign_keys: BTreeMap::new(),
+            arguments,
+            argument_descriptions: BTreeMap::new(),
+            function: None,
+            containment: Some(Containment {
+                parent: parent.name.clone(),
+                navigation: navigation_property.name.clone(),
+                key_argument,
+            }),
+            type_cast: None,
+            restrictions: super::Restrictions::default(),
+        };
+
+        ancestry.push(target_type);
+        contained(
+            metadata,
+            &mut collection,
+            &navigation_property.name,
+            ancestry,
+            collections,
+        );
+        ancestry.pop();
+
+        // We can `$expand` the navigation property from the parent, just like any other.
+        parent
+            .relationships
+            .insert(navigation_property.name.clone(), name.clone());
+
+        collections.insert(name, collection);
+    }
+}
+
+/// The collection that a navigation property binding leads to: the entity set it targets, which
+/// may be in another container. If we can't find the target, we keep it as it is, so that
+/// validation can report it.
+pub fn binding_target(
+    metadata: &odata::EDMX,
+    schema: &schema::Schema,
+    binding: &odata::NavigationPropertyBinding,
+) -> String {
+    match metadata.binding_target(schema, &binding.target) {
+        Some(entity_set) => entity_set.name,
+        None => binding.target.clone(),
+    }
+}
+
+/// The columns behind each navigation property binding of an entity set, mapping the properties of
+/// the entity set's entity type to those of the target. Navigation properties without referential
+/// constraints don't have any: they're relationships that we can only follow with `$expand`.
+fn foreign_keys(
+    metadata: &odata::EDMX,
+    entity_type: &odata::EntityType,
+    entity_set: &odata::EntitySet,
+) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut foreign_keys = BTreeMap::new();
+
+    for binding in &entity_set.navigation_property_bindings {
+        let constraints = match entity_type.navigation_property(metadata, &binding.path) {
+            Some(navigation_property) => navigation_property.referential_constraints,
+            None => continue,
+        };
+
+        // Constraints on properties nested within complex properties don't relate columns.
+        let is_columns = constraints.iter().all(|constraint| {
+            !constraint.property.contains('/') && !constraint.referenced_property.contains('/')
+        });
+
+        if constraints.is_empty() || !is_columns {
+            continue;
+        }
+
+        let column_mapping = constraints
+            .into_iter()
+            .map(|constraint| (constraint.property, constraint.referenced_property))
+            .collect();
+
+        foreign_keys.insert(binding.path.clone(), column_mapping);
+    }
+
+    foreign_keys
+}
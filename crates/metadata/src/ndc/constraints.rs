@@ -0,0 +1,169 @@
+//! Facets and the Validation vocabulary tell us which values a property or parameter accepts: a
+//! string's maximum length or pattern, a number's bounds, and so on. We keep track of these so that
+//! we can reject invalid input ourselves, with an error that says which field is at fault.
+
+use crate::odata;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const VALIDATION: &str = "Org.OData.Validation.V1";
+
+/// The constraints on the values of a field or argument. Anything we don't know is unconstrained.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Constraints {
+    /// The maximum length of a string in characters, or of binary data in bytes.
+    pub max_length: Option<usize>,
+
+    /// The maximum number of significant digits of a decimal, or of fractional seconds of a
+    /// temporal value.
+    pub precision: Option<usize>,
+
+    /// The maximum number of digits to the right of a decimal's point.
+    pub scale: Option<usize>,
+
+    /// The spatial reference system of a geography or geometry value.
+    pub srid: Option<u32>,
+
+    /// A regular expression that strings must match.
+    pub pattern: Option<String>,
+
+    pub minimum: Option<Bound>,
+    pub maximum: Option<Bound>,
+
+    /// The only values that are allowed, written as they appear in the metadata.
+    pub allowed_values: Option<Vec<String>>,
+}
+
+/// The lower or upper limit of a value.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct Bound {
+    pub value: String,
+
+    /// Is the limit itself excluded?
+    #[serde(default)]
+    pub exclusive: bool,
+}
+
+impl Constraints {
+    /// Read the constraints of a property or parameter from its facets and its annotations, which
+    /// may be written inline or out of line. If nothing constrains it, we return nothing.
+    pub fn extract_from(
+        metadata: &odata::EDMX,
+        target: &str,
+        type_data: &odata::TypeData,
+        inline: &[odata::Annotation],
+    ) -> Option<Self> {
+        let annotations = metadata.applicable_annotations(target, inline);
+        let term = |name: &str| odata::find_term(&annotations, VALIDATION, name);
+
+        let bound = |name: &str| {
+            let annotation = term(name)?;
+            let exclusive = format!("{VALIDATION}.Exclusive");
+
+            Some(Bound {
+                value: annotation.constant()?.to_string(),
+                exclusive: annotation
+                    .annotations
+                    .iter()
+                    .any(|nested| nested.term == exclusive && nested.is_set()),
+            })
+        };
+
+        let allowed_values = term("AllowedValues")
+            .and_then(|annotation| annotation.collection.as_ref())
+            .map(|collection| {
+                collection
+                    .records
+                    .iter()
+                    .filter_map(|record| record.property("Value"))
+                    .filter_map(odata::PropertyValue::constant)
+                    .map(String::from)
+                    .collect()
+            });
+
+        let facets = &type_data.facets;
+
+        let constraints = Constraints {
+            max_length: number(&facets.max_length),
+            precision: number(&facets.precision),
+            scale: number(&facets.scale),
+            srid: number(&facets.srid),
+            pattern: term("Pattern")
+                .and_then(odata::Annotation::string)
+                .map(String::from),
+            minimum: bound("Minimum"),
+            maximum: bound("Maximum"),
+            allowed_values,
+        };
+
+        (!constraints.is_empty()).then_some(constraints)
+    }
+
+    /// Are there no constraints at all?
+    pub fn is_empty(&self) -> bool {
+        self.max_length.is_none()
+            && self.precision.is_none()
+            && self.scale.is_none()
+            && self.srid.is_none()
+            && self.pattern.is_none()
+            && self.minimum.is_none()
+            && self.maximum.is_none()
+            && self.allowed_values.is_none()
+    }
+}
+
+/// Read a numeric facet. Symbolic facet values, such as `max` or `variable`, don't constrain
+/// anything.
+fn number<T: std::str::FromStr>(facet: &Option<String>) -> Option<T> {
+    facet.as_ref().and_then(|facet| facet.parse().ok())
+}
+
+/// The constraints on the parameters of a function or action, which out-of-line annotations
+/// target as `Trippin.GetNearestAirport/lat`.
+pub fn parameters(
+    metadata: &odata::EDMX,
+    operation: &str,
+    parameters: &[odata::Parameter],
+) -> BTreeMap<String, Constraints> {
+    let mut constraints = BTreeMap::new();
+
+    for parameter in parameters {
+        let target = format!("{operation}/{}", parameter.name);
+
+        if let Some(found) =
+            Constraints::extract_from(metadata, &target, &parameter.r#type, &parameter.annotations)
+        {
+            constraints.insert(parameter.name.clone(), found);
+        }
+    }
+
+    constraints
+}
+
+/// The constraints on the properties of an entity type or complex type, including those it
+/// inherits.
+pub fn properties(
+    metadata: &odata::EDMX,
+    structured_type: &odata::QualifiedType,
+) -> BTreeMap<String, Constraints> {
+    let mut constraints = BTreeMap::new();
+
+    for (declaring_type, properties) in metadata.declaring_types(structured_type) {
+        for property in properties {
+            let target = format!("{declaring_type}/{}", property.name);
+
+            if let Some(found) = Constraints::extract_from(
+                metadata,
+                &target,
+                &property.r#type,
+                &property.annotations,
+            ) {
+                constraints.insert(property.name, found);
+            }
+        }
+    }
+
+    constraints
+}
@@ -0,0 +1,42 @@
+//! Entity sets are polymorphic: the entities of `People` may be of type `Trippin.Person`, or of any
+//! type derived from it, such as `Trippin.Employee`. We expose each derived type as a collection of
+//! its own, addressed with a type cast (`/People/Trippin.Employee`), and we tell the types apart in
+//! the base collection with a discriminator column.
+
+use crate::ndc;
+use crate::odata;
+use std::collections::BTreeSet;
+
+/// The column that holds the type of each entity. OData only sends this annotation when the type
+/// differs from the one it expects, so otherwise we fill it in ourselves.
+pub const DISCRIMINATOR: &str = "@odata.type";
+
+/// The scalar type of the discriminator column: a namespace-qualified entity type name.
+pub fn type_name_type() -> ndc::QualifiedType {
+    ndc::QualifiedType {
+        schema: ndc::mutations::NAMESPACE.to_string(),
+        name: "TypeName".to_string(),
+    }
+}
+
+/// Is this entity type part of an inheritance hierarchy? If so, its object type has a
+/// discriminator column.
+pub fn is_polymorphic(metadata: &odata::EDMX, entity_type: &odata::QualifiedType) -> bool {
+    metadata.ancestry(entity_type).len() > 1 || !metadata.derived_types(entity_type).is_empty()
+}
+
+/// The scalar types that the discriminator needs: every hierarchy has at least one entity type
+/// with a base type.
+pub fn scalar_types(schema: &odata::Schema) -> BTreeSet<ndc::ScalarType> {
+    let mut scalar_types = BTreeSet::new();
+
+    if schema
+        .entity_types
+        .iter()
+        .any(|entity_type| entity_type.base_type.is_some())
+    {
+        scalar_types.insert(ndc::ScalarType(type_name_type().to_string()));
+    }
+
+    scalar_types
+}
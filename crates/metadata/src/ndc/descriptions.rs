@@ -0,0 +1,127 @@
+//! Services document their models with vocabulary annotations, which we turn into the descriptions
+//! that GraphQL consumers see. A description is made up of a summary (`Core.Description`, or
+//! failing that, `Common.Label`), a longer explanation (`Core.LongDescription`), and the currency
+//! or unit of measure of a value (`Measures.ISOCurrency` and `Measures.Unit`).
+
+use crate::odata;
+use std::collections::BTreeMap;
+
+const CORE: &str = "Org.OData.Core.V1";
+const COMMON: &str = "com.sap.vocabularies.Common.v1";
+const MEASURES: &str = "Org.OData.Measures.V1";
+
+/// Describe a model element using its inline annotations, along with any out-of-line annotations
+/// that target it.
+pub fn describe(
+    metadata: &odata::EDMX,
+    target: &str,
+    inline: &[odata::Annotation],
+) -> Option<String> {
+    let annotations = metadata.applicable_annotations(target, inline);
+    let term = |namespace: &str, name: &str| odata::find_term(&annotations, namespace, name);
+    let string = |namespace: &str, name: &str| term(namespace, name).and_then(|x| x.string());
+    let mut paragraphs = Vec::new();
+
+    if let Some(summary) = string(CORE, "Description").or_else(|| string(COMMON, "Label")) {
+        paragraphs.push(summary.to_string());
+    }
+
+    if let Some(long_description) = string(CORE, "LongDescription") {
+        paragraphs.push(long_description.to_string());
+    }
+
+    // Measures can be fixed, or given by another property of the same entity.
+    for (name, label) in [("ISOCurrency", "Currency"), ("Unit", "Unit")] {
+        if let Some(measure) = term(MEASURES, name) {
+            match (measure.string(), measure.path()) {
+                (Some(value), _) => paragraphs.push(format!("{label}: {value}.")),
+                (None, Some(path)) => paragraphs.push(format!("{label}: given by {path}.")),
+                (None, None) => {}
+            }
+        }
+    }
+
+    (!paragraphs.is_empty()).then(|| paragraphs.join("\n\n"))
+}
+
+/// Describe a child of a schema's entity container, such as an entity set or function import.
+/// Out-of-line annotations target these through the container: `Trippin.Container/People`.
+pub fn container_child(
+    metadata: &odata::EDMX,
+    schema: &odata::Schema,
+    name: &str,
+    annotations: &[odata::Annotation],
+) -> Option<String> {
+    let target = format!(
+        "{}.{}/{name}",
+        schema.namespace, schema.entity_container.name
+    );
+
+    describe(metadata, &target, annotations)
+}
+
+/// Describe the parameters of a function or action, which out-of-line annotations target as
+/// `Trippin.GetNearestAirport/lat`.
+pub fn parameters(
+    metadata: &odata::EDMX,
+    operation: &str,
+    parameters: &[odata::Parameter],
+) -> BTreeMap<String, String> {
+    let mut descriptions = BTreeMap::new();
+
+    for parameter in parameters {
+        let target = format!("{operation}/{}", parameter.name);
+
+        if let Some(description) = describe(metadata, &target, &parameter.annotations) {
+            descriptions.insert(parameter.name.clone(), description);
+        }
+    }
+
+    descriptions
+}
+
+/// Describe the properties of an entity type or complex type, including those it inherits, along
+/// with their default values.
+pub fn properties(
+    metadata: &odata::EDMX,
+    structured_type: &odata::QualifiedType,
+) -> BTreeMap<String, String> {
+    let mut descriptions = BTreeMap::new();
+
+    for (declaring_type, properties) in metadata.declaring_types(structured_type) {
+        for property in properties {
+            let target = format!("{declaring_type}/{}", property.name);
+            let mut paragraphs: Vec<String> = describe(metadata, &target, &property.annotations)
+                .into_iter()
+                .collect();
+
+            // Consumers can't see what the service fills in when a property is omitted, so we say.
+            if let Some(default_value) = &property.r#type.facets.default_value {
+                paragraphs.push(format!("Default: {default_value}."));
+            }
+
+            if !paragraphs.is_empty() {
+                descriptions.insert(property.name, paragraphs.join("\n\n"));
+            }
+        }
+    }
+
+    descriptions
+}
+
+/// Describe an entity type or complex type.
+pub fn structured_type(
+    metadata: &odata::EDMX,
+    structured_type: &odata::QualifiedType,
+) -> Option<String> {
+    let annotations = match (
+        metadata.entity_type(structured_type),
+        metadata.complex_type(structured_type),
+    ) {
+        (Some(entity_type), _) => entity_type.annotations,
+        (None, Some(complex_type)) => complex_type.annotations,
+        (None, None) => Vec::new(),
+    };
+
+    describe(metadata, &structured_type.to_string(), &annotations)
+}
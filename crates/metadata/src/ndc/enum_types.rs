@@ -0,0 +1,44 @@
+//! Functions relating to extracting enum types from OData metadata.
+
+use crate::odata;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Enum types are scalar types in the `ndc-spec` schema, but we keep track of their members so
+/// that we can write (and check) enum literals in filters, which look like `Trippin.Gender'Male'`.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct EnumType {
+    pub members: Vec<String>,
+
+    /// The integer type that underlies the enum's values, such as `Edm.Int32`.
+    pub underlying_type: String,
+
+    /// Can a value combine several members? If so, we can filter with the `has` operator.
+    #[serde(default)]
+    pub is_flags: bool,
+}
+
+impl EnumType {
+    /// Collect the enum types declared in a schema, keyed by their qualified names.
+    pub fn extract_from(schema: &odata::Schema) -> BTreeMap<String, EnumType> {
+        let mut enum_types = BTreeMap::new();
+
+        for enum_type in &schema.enum_types {
+            enum_types.insert(
+                format!("{}.{}", schema.namespace, enum_type.name),
+                EnumType {
+                    members: enum_type
+                        .members
+                        .iter()
+                        .map(|member| member.name.clone())
+                        .collect(),
+                    underlying_type: enum_type.underlying_type().to_string(),
+                    is_flags: enum_type.is_flags,
+                },
+            );
+        }
+
+        enum_types
+    }
+}
@@ -1,5 +1,5 @@
 use crate::ndc;
-use crate::odata::{functions, schema};
+use crate::odata::{self, functions, schema};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -9,27 +9,272 @@ pub struct Function {
     pub name: String,
     pub arguments: BTreeMap<String, ndc::Type>,
     pub result_type: ndc::Type,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub argument_descriptions: BTreeMap<String, String>,
+
+    /// If this is a bound function, the collection it is bound to.
+    #[serde(default)]
+    pub binding: Option<Binding>,
+
+    /// If this is an unbound function, the name of the function import through which we invoke
+    /// it. This can differ from the function's name when the function is overloaded.
+    #[serde(default)]
+    pub import: Option<String>,
+
+    /// If this function reads a singleton, the name of that singleton. Singletons are addressed
+    /// by name alone, with no parentheses.
+    #[serde(default)]
+    pub singleton: Option<String>,
+
+    /// If this function reads a singleton, the collection that each of its navigation properties
+    /// leads to, according to the singleton's navigation property bindings.
+    #[serde(default)]
+    pub relationships: BTreeMap<String, String>, // navigation property => collection
+}
+
+/// The name under which we expose one overload of a function. When there's only one overload, we
+/// use the name as it is. Otherwise, each overload must have a different set of parameters, so we
+/// append the parameter names to get a name that is both unique and stable.
+pub fn overload_name(name: &str, parameters: &[functions::Parameter], overloaded: bool) -> String {
+    let mut overload_name = name.to_string();
+
+    if overloaded {
+        for parameter in parameters {
+            overload_name.push('_');
+            overload_name.push_str(&parameter.name);
+        }
+    }
+
+    overload_name
+}
+
+/// Bound functions and actions can't be invoked from the service root: they have to be invoked
+/// on the entity (or collection of entities) to which they're bound, as in
+/// `/People('russellwhyte')/Trippin.GetFavoriteAirline()`. We expose one operation per entity set
+/// that has the binding parameter's type, and the binding parameter becomes an argument: either
+/// the key of the entity, or a predicate that picks out the entities in the collection.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct Binding {
+    /// The entity set to which the operation is bound.
+    pub collection: String,
+
+    /// The name of the binding parameter, which we reuse as the name of the argument.
+    pub parameter: String,
+
+    /// Is the operation bound to the whole collection, rather than to a single entity?
+    pub is_collection: bool,
+
+    /// The namespace-qualified name of the operation. Bound operations must be invoked by their
+    /// qualified name.
+    pub operation: String,
+}
+
+impl Binding {
+    /// Find every entity set to which an operation can be bound, along with the type of the
+    /// argument that replaces the binding parameter. The binding parameter is always the first.
+    pub fn extract_from(
+        metadata: &odata::EDMX,
+        schema: &schema::Schema,
+        operation: &str,
+        parameters: &[functions::Parameter],
+    ) -> Vec<(Binding, ndc::Type)> {
+        let mut bindings = Vec::new();
+
+        let binding_parameter = match parameters.first() {
+            Some(binding_parameter) => binding_parameter,
+            None => return bindings,
+        };
+
+        let entity_type_name = binding_parameter.r#type.underlying_type();
+        let is_collection = matches!(
+            binding_parameter.r#type.inner,
+            odata::Type::Collection { .. }
+        );
+
+        if metadata.entity_type(entity_type_name).is_none() {
+            return bindings;
+        }
+
+        // A collection-bound operation takes a predicate over its collection (or null for the
+        // whole thing), and an entity-bound operation takes the key of its entity.
+        let argument_type = if is_collection {
+            ndc::Type::Qualified {
+                qualified_type: ndc::mutations::expression_type(),
+            }
+            .optional()
+        } else {
+            ndc::keys::key_type(metadata, entity_type_name)
+        };
+
+        // The operation may be declared in a different schema (or document) to the container
+        // whose entity sets it binds to.
+        let entity_sets = metadata
+            .data_services
+            .schema
+            .iter()
+            .flat_map(|schema| schema.entity_container.entity_sets.iter());
+
+        for entity_set in entity_sets {
+            if &entity_set.entity_type == entity_type_name {
+                let binding = Binding {
+                    collection: entity_set.name.clone(),
+                    parameter: binding_parameter.name.clone(),
+                    is_collection,
+                    operation: format!("{}.{}", schema.namespace, operation),
+                };
+
+                bindings.push((binding, argument_type.clone()));
+            }
+        }
+
+        bindings
+    }
 }
 
 impl Function {
-    pub fn extract_from(schema: &schema::Schema) -> Vec<ndc::Function> {
+    pub fn extract_from(metadata: &odata::EDMX, schema: &schema::Schema) -> Vec<ndc::Function> {
         let mut functions = Vec::new();
 
-        for function in &schema.functions {
-            let mut arguments = BTreeMap::new();
-            let result_type = super::Type::from_type_data(&function.return_type);
+        // Unbound functions are only available through the function imports of the entity
+        // container, and they're invoked by the import's name rather than the function's.
+        for function_import in &schema.entity_container.function_imports {
+            let overloads = metadata.unbound_functions(&function_import.function);
 
-            for functions::Parameter { name, r#type } in &function.parameters {
-                arguments.insert(name.clone(), super::Type::from_type_data(r#type));
+            for function in &overloads {
+                // Composable functions that return entities are exposed as collections instead.
+                if function.entity_collection_type(metadata).is_some() {
+                    continue;
+                }
+
+                // The import's own documentation takes precedence over the function's.
+                let description = ndc::descriptions::container_child(
+                    metadata,
+                    schema,
+                    &function_import.name,
+                    &function_import.annotations,
+                )
+                .or_else(|| {
+                    let target = &function_import.function;
+                    ndc::descriptions::describe(metadata, target, &function.annotations)
+                });
+
+                functions.push(ndc::Function {
+                    name: overload_name(
+                        &function_import.name,
+                        &function.parameters,
+                        overloads.len() > 1,
+                    ),
+                    arguments: Self::arguments(function),
+                    result_type: super::Type::from_type_data(&function.return_type),
+                    description,
+                    argument_descriptions: ndc::descriptions::parameters(
+                        metadata,
+                        &function_import.function,
+                        &function.parameters,
+                    ),
+                    binding: None,
+                    import: Some(function_import.name.clone()),
+                    singleton: None,
+                    relationships: BTreeMap::new(),
+                });
             }
+        }
+
+        // Bound functions are exposed once for each entity set they can be bound to, with the
+        // binding parameter replaced by the key or predicate that identifies the binding.
+        for function in schema.functions.iter().filter(|function| function.is_bound) {
+            let overloaded = schema
+                .functions
+                .iter()
+                .filter(|overload| overload.is_bound && overload.name == function.name)
+                .count()
+                > 1;
+
+            let qualified_name = format!("{}.{}", schema.namespace, function.name);
+            let description =
+                ndc::descriptions::describe(metadata, &qualified_name, &function.annotations);
+            let argument_descriptions =
+                ndc::descriptions::parameters(metadata, &qualified_name, &function.parameters);
+
+            for (binding, argument_type) in
+                Binding::extract_from(metadata, schema, &function.name, &function.parameters)
+            {
+                let mut arguments = Self::arguments(function);
+                arguments.insert(binding.parameter.clone(), argument_type);
+
+                // The binding parameter is already reflected in the collection prefix.
+                let name = overload_name(
+                    &format!("{}_{}", binding.collection, function.name),
+                    &function.parameters[1..],
+                    overloaded,
+                );
+
+                functions.push(ndc::Function {
+                    name,
+                    arguments,
+                    result_type: super::Type::from_type_data(&function.return_type),
+                    description: description.clone(),
+                    argument_descriptions: argument_descriptions.clone(),
+                    binding: Some(binding),
+                    import: None,
+                    singleton: None,
+                    relationships: BTreeMap::new(),
+                });
+            }
+        }
+
+        // Singletons are entities that we can read without a key, so they behave like nullary
+        // functions. Queries against them can still `$select` and `$expand`, and the targets of
+        // their navigation property bindings tell us where their relationships lead.
+        for singleton in &schema.entity_container.singletons {
+            let relationships = singleton
+                .navigation_property_bindings
+                .iter()
+                .map(|binding| {
+                    let target = ndc::collections::binding_target(metadata, schema, binding);
+                    (binding.path.clone(), target)
+                })
+                .collect();
+
+            let description = ndc::descriptions::container_child(
+                metadata,
+                schema,
+                &singleton.name,
+                &singleton.annotations,
+            )
+            .or_else(|| {
+                let entity_type = singleton.r#type.underlying_type();
+                ndc::descriptions::structured_type(metadata, entity_type)
+            });
 
             functions.push(ndc::Function {
-                name: function.name.clone(),
-                arguments,
-                result_type,
+                name: singleton.name.clone(),
+                arguments: BTreeMap::new(),
+                result_type: super::Type::from_type_data(&singleton.r#type),
+                description,
+                argument_descriptions: BTreeMap::new(),
+                binding: None,
+                import: None,
+                singleton: Some(singleton.name.clone()),
+                relationships,
             });
         }
 
         functions
     }
+
+    /// The NDC arguments for each of a function's parameters.
+    fn arguments(function: &functions::Function) -> BTreeMap<String, ndc::Type> {
+        let mut arguments = BTreeMap::new();
+
+        for functions::Parameter { name, r#type, .. } in &function.parameters {
+            arguments.insert(name.clone(), super::Type::from_type_data(r#type));
+        }
+
+        arguments
+    }
 }
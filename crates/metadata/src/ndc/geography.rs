@@ -0,0 +1,129 @@
+//! Geographic and geometric types. OData services represent spatial values as GeoJSON, and they
+//! let us filter on them with functions such as `geo.distance`. The NDC only lets us compare a
+//! column with a value, so we expose these functions as custom comparison operators on the
+//! spatial scalar types, along with any types that their arguments need.
+
+use crate::ndc;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The OData comparison operators that we support for `geo.distance` and `geo.length`.
+pub const COMPARISONS: [&str; 4] = ["lt", "le", "gt", "ge"];
+
+/// Split a spatial type name into its family and its shape: `Edm.GeographyPoint` is a `Point` in
+/// the `Geography` family.
+pub fn family(scalar_type: &str) -> Option<(&'static str, &str)> {
+    let name = scalar_type.strip_prefix("Edm.")?;
+
+    for family in ["Geography", "Geometry"] {
+        if let Some(shape) = name.strip_prefix(family) {
+            return Some((family, shape));
+        }
+    }
+
+    None
+}
+
+/// The argument to the `distance_` operators: a point, and the distance from it to compare with.
+pub fn distance_type(family: &str) -> ndc::QualifiedType {
+    ndc::QualifiedType {
+        schema: super::mutations::NAMESPACE.to_string(),
+        name: format!("{family}Distance"),
+    }
+}
+
+/// The custom comparison operators for a scalar type, along with their argument types. Points
+/// can be compared by distance (`distance_lt`) or tested against a polygon (`intersects`), and
+/// line strings can be compared by length (`length_lt`).
+pub fn comparison_operators(scalar_type: &str) -> BTreeMap<String, ndc::Type> {
+    let mut operators = BTreeMap::new();
+
+    let edm = |name: String| ndc::Type::Qualified {
+        qualified_type: ndc::QualifiedType {
+            schema: "Edm".to_string(),
+            name,
+        },
+    };
+
+    match family(scalar_type) {
+        Some((family, "Point")) => {
+            operators.insert("intersects".to_string(), edm(format!("{family}Polygon")));
+
+            for name in COMPARISONS {
+                let argument_type = ndc::Type::Qualified {
+                    qualified_type: distance_type(family),
+                };
+
+                operators.insert(format!("distance_{name}"), argument_type);
+            }
+        }
+
+        Some((_, "LineString")) => {
+            for name in COMPARISONS {
+                operators.insert(format!("length_{name}"), edm("Double".to_string()));
+            }
+        }
+
+        _ => {}
+    }
+
+    operators
+}
+
+/// The scalar types that the operators of the given scalar types need for their arguments.
+pub fn scalar_types(scalar_types: &BTreeSet<ndc::ScalarType>) -> BTreeSet<ndc::ScalarType> {
+    let mut argument_types = BTreeSet::new();
+
+    for scalar_type in scalar_types {
+        match family(&scalar_type.0) {
+            Some((family, "Point")) => {
+                argument_types.insert(ndc::ScalarType(format!("Edm.{family}Polygon")));
+                argument_types.insert(ndc::ScalarType("Edm.Double".to_string()));
+            }
+
+            Some((_, "LineString")) => {
+                argument_types.insert(ndc::ScalarType("Edm.Double".to_string()));
+            }
+
+            _ => {}
+        }
+    }
+
+    argument_types
+}
+
+/// The object types that the operators of the given scalar types need for their arguments.
+pub fn object_types(scalar_types: &BTreeSet<ndc::ScalarType>) -> BTreeMap<String, ndc::ObjectType> {
+    let mut object_types = BTreeMap::new();
+
+    for scalar_type in scalar_types {
+        if let Some((family, "Point")) = family(&scalar_type.0) {
+            let from = ndc::Type::Qualified {
+                qualified_type: ndc::QualifiedType {
+                    schema: "Edm".to_string(),
+                    name: format!("{family}Point"),
+                },
+            };
+
+            let distance = ndc::Type::Qualified {
+                qualified_type: ndc::QualifiedType {
+                    schema: "Edm".to_string(),
+                    name: "Double".to_string(),
+                },
+            };
+
+            object_types.insert(
+                distance_type(family).to_string(),
+                ndc::ObjectType {
+                    description: None,
+                    base_type: None,
+                    fields: BTreeMap::from([
+                        ("distance".to_string(), distance.into()),
+                        ("from".to_string(), from.into()),
+                    ]),
+                },
+            );
+        }
+    }
+
+    object_types
+}
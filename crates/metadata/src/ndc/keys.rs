@@ -0,0 +1,72 @@
+//! Entities are addressed by their keys. A key with a single part is just a value of that part's
+//! type, but a composite key needs an object type of its own, with a field for each part. These
+//! keys are the arguments of entity-bound functions and of the procedures that manage links.
+
+use crate::ndc;
+use crate::odata;
+use std::collections::BTreeMap;
+
+/// The type of the object that we accept as the composite key of the given entity type.
+pub fn composite_key_type(entity_type: &odata::QualifiedType) -> ndc::QualifiedType {
+    ndc::QualifiedType {
+        schema: entity_type.schema.clone(),
+        name: format!("{}_key", entity_type.name),
+    }
+}
+
+/// The type of the key of the given entity type.
+pub fn key_type(metadata: &odata::EDMX, entity_type: &odata::QualifiedType) -> ndc::Type {
+    let key_types = metadata
+        .entity_type(entity_type)
+        .map(|target| target.key_types(metadata))
+        .unwrap_or_default();
+
+    let qualified_type = match key_types.as_slice() {
+        [(_, key_type)] => key_type.into(),
+        _ => composite_key_type(entity_type),
+    };
+
+    ndc::Type::Qualified { qualified_type }
+}
+
+/// The object types for the composite keys of the entity types in this schema.
+pub fn object_types(
+    metadata: &odata::EDMX,
+    schema: &odata::Schema,
+) -> BTreeMap<String, ndc::ObjectType> {
+    let mut object_types = BTreeMap::new();
+
+    for entity_type in &schema.entity_types {
+        let key_types = entity_type.key_types(metadata);
+
+        if key_types.len() < 2 {
+            continue;
+        }
+
+        let fields = key_types
+            .iter()
+            .map(|(property_ref, key_type)| {
+                let field_type = ndc::Type::Qualified {
+                    qualified_type: key_type.into(),
+                };
+
+                (property_ref.key_name().to_string(), field_type.into())
+            })
+            .collect();
+
+        let name = composite_key_type(&odata::QualifiedType {
+            schema: schema.namespace.clone(),
+            name: entity_type.name.clone(),
+        });
+
+        let object_type = ndc::ObjectType {
+            description: None,
+            base_type: None,
+            fields,
+        };
+
+        object_types.insert(name.to_string(), object_type);
+    }
+
+    object_types
+}
@@ -0,0 +1,476 @@
+//! Procedures that the connector generates for writing to collections. OData services let us
+//! insert, update, and delete the entities of an entity set (and update singletons), but these
+//! operations aren't described as actions in the metadata, so we invent a set of procedures for
+//! each entity set, as well as the input and output types those procedures need.
+
+use crate::ndc;
+use crate::odata;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The namespace for any types that the connector invents, rather than finding in the metadata.
+pub const NAMESPACE: &str = "OData";
+
+/// The scalar type of a predicate argument. Its values are `ndc-spec` expressions in their JSON
+/// form, which we translate into OData filters exactly as we would for a query.
+pub fn expression_type() -> ndc::QualifiedType {
+    ndc::QualifiedType {
+        schema: NAMESPACE.to_string(),
+        name: "Expression".to_string(),
+    }
+}
+
+/// The object type returned by the generated procedures: the number of entities affected.
+pub fn mutation_result_type() -> ndc::QualifiedType {
+    ndc::QualifiedType {
+        schema: NAMESPACE.to_string(),
+        name: "MutationResult".to_string(),
+    }
+}
+
+/// The type of the objects we accept for insertion into an entity set of the given entity type.
+pub fn insert_type(entity_type: &odata::QualifiedType) -> ndc::QualifiedType {
+    ndc::QualifiedType {
+        schema: entity_type.schema.clone(),
+        name: format!("{}_insert", entity_type.name),
+    }
+}
+
+/// The type of the changes we accept for updating entities of the given entity type.
+pub fn update_type(entity_type: &odata::QualifiedType) -> ndc::QualifiedType {
+    ndc::QualifiedType {
+        schema: entity_type.schema.clone(),
+        name: format!("{}_update", entity_type.name),
+    }
+}
+
+/// The scalar types that the generated procedures rely upon.
+pub fn scalar_types(schema: &odata::Schema) -> BTreeSet<ndc::ScalarType> {
+    let mut scalar_types = BTreeSet::new();
+
+    if !schema.entity_container.entity_sets.is_empty() {
+        scalar_types.insert(ndc::ScalarType(expression_type().to_string()));
+    }
+
+    if !schema.entity_container.entity_sets.is_empty()
+        || !schema.entity_container.singletons.is_empty()
+        || has_void_actions(schema)
+    {
+        scalar_types.insert(ndc::ScalarType("Edm.Int32".to_string()));
+    }
+
+    scalar_types
+}
+
+/// Actions without a return type also return an `OData.MutationResult`, so we need its types
+/// even when there's nothing to write to.
+fn has_void_actions(schema: &odata::Schema) -> bool {
+    schema
+        .actions
+        .iter()
+        .any(|action| action.return_type.is_none())
+}
+
+/// The input types for the entity type of each entity set, singleton, and contained collection, as
+/// well as the shared result type. The collections are those that we extracted from this schema.
+pub fn object_types(
+    metadata: &odata::EDMX,
+    schema: &odata::Schema,
+    collections: &[ndc::Collection],
+) -> BTreeMap<String, ndc::ObjectType> {
+    let mut object_types = BTreeMap::new();
+
+    let entity_set_types = schema
+        .entity_container
+        .entity_sets
+        .iter()
+        .map(|entity_set| &entity_set.entity_type);
+
+    let singleton_types = schema
+        .entity_container
+        .singletons
+        .iter()
+        .map(|singleton| singleton.r#type.underlying_type());
+
+    let contained_types: Vec<odata::QualifiedType> = contained_collections(collections)
+        .into_iter()
+        .map(|(_, entity_type)| entity_type)
+        .collect();
+
+    for entity_type_name in entity_set_types
+        .chain(singleton_types)
+        .chain(contained_types.iter())
+    {
+        // Validation reports collections whose entity types don't exist.
+        let entity_type = match metadata.entity_type(entity_type_name) {
+            Some(entity_type) => entity_type,
+            None => continue,
+        };
+
+        let mut descriptions = ndc::descriptions::properties(metadata, entity_type_name);
+        let mut constraints = ndc::constraints::properties(metadata, entity_type_name);
+        let read_only = ndc::read_only::properties(metadata, entity_type_name);
+        let mut insert_fields = BTreeMap::new();
+        let mut update_fields = BTreeMap::new();
+
+        let key: Vec<String> = entity_type
+            .key(metadata)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|part| part.name.split('/').next().map(String::from))
+            .collect();
+
+        for property in entity_type.fields(metadata) {
+            let field_type = ndc::Type::from_type_data(&property.r#type);
+            let description = descriptions.remove(&property.name);
+            let constraints = constraints.remove(&property.name);
+
+            let field = |r#type: ndc::Type| ndc::ObjectField {
+                r#type,
+                description: description.clone(),
+                constraints: constraints.clone(),
+                read_only: None,
+            };
+
+            // The service won't accept values for read-only properties, with one exception: it
+            // generates computed keys when we don't give them, but we may still choose a key.
+            match read_only.get(&property.name) {
+                None => {
+                    update_fields
+                        .insert(property.name.clone(), field(field_type.clone().optional()));
+                    insert_fields.insert(property.name, field(field_type));
+                }
+
+                Some(ndc::ReadOnly::Computed) if key.contains(&property.name) => {
+                    insert_fields.insert(property.name, field(field_type.optional()));
+                }
+
+                Some(_) => {}
+            }
+        }
+
+        // We can't create entities of an abstract type, only of the types that extend it.
+        if !entity_type.is_abstract {
+            object_types.insert(
+                insert_type(entity_type_name).to_string(),
+                ndc::ObjectType {
+                    description: None,
+                    base_type: None,
+                    fields: insert_fields,
+                },
+            );
+        }
+
+        object_types.insert(
+            update_type(entity_type_name).to_string(),
+            ndc::ObjectType {
+                description: None,
+                base_type: None,
+                fields: update_fields,
+            },
+        );
+    }
+
+    if !object_types.is_empty() || has_void_actions(schema) {
+        let affected_rows = ndc::Type::Qualified {
+            qualified_type: ndc::QualifiedType {
+                schema: "Edm".to_string(),
+                name: "Int32".to_string(),
+            },
+        };
+
+        object_types.insert(
+            mutation_result_type().to_string(),
+            ndc::ObjectType {
+                description: None,
+                base_type: None,
+                fields: BTreeMap::from([(
+                    "affected_rows".to_string(),
+                    ndc::ObjectField {
+                        r#type: affected_rows,
+                        description: Some("The number of entities affected.".to_string()),
+                        constraints: None,
+                        read_only: None,
+                    },
+                )]),
+            },
+        );
+    }
+
+    object_types
+}
+
+/// The insert, update, and delete procedures for every entity set and contained collection in the
+/// schema, as well as the procedures for managing the relationships bound to each entity set. The
+/// collections are those that we extracted from this schema.
+pub fn procedures(
+    metadata: &odata::EDMX,
+    schema: &odata::Schema,
+    collections: &[ndc::Collection],
+) -> Vec<ndc::Procedure> {
+    let mut procedures = Vec::new();
+
+    let result_type = ndc::Type::Qualified {
+        qualified_type: mutation_result_type(),
+    };
+
+    for entity_set in &schema.entity_container.entity_sets {
+        let restrictions = ndc::Restrictions::extract_from(metadata, schema, entity_set);
+
+        procedures.append(&mut collection_procedures(
+            metadata,
+            &entity_set.name,
+            &entity_set.entity_type,
+            &BTreeMap::new(),
+            &restrictions,
+        ));
+
+        procedures.append(&mut reference_procedures(
+            metadata,
+            schema,
+            entity_set,
+            &restrictions,
+        ));
+    }
+
+    // Contained entities are written through their parent, so the procedures for a contained
+    // collection take the same arguments as the collection itself.
+    for (collection, entity_type) in contained_collections(collections) {
+        procedures.append(&mut collection_procedures(
+            metadata,
+            &collection.name,
+            &entity_type,
+            &collection.arguments,
+            &collection.restrictions,
+        ));
+    }
+
+    // Singletons can't be created or deleted, but they can be updated with a `PATCH`.
+    for singleton in &schema.entity_container.singletons {
+        let set = ndc::Type::Qualified {
+            qualified_type: update_type(singleton.r#type.underlying_type()),
+        };
+
+        procedures.push(ndc::Procedure {
+            name: format!("update_{}", singleton.name),
+            arguments: BTreeMap::from([("set".to_string(), set)]),
+            result_type: result_type.clone(),
+            operation: ndc::Operation::UpdateSingleton {
+                singleton: singleton.name.clone(),
+            },
+            description: None,
+            argument_descriptions: BTreeMap::new(),
+            argument_constraints: BTreeMap::new(),
+        });
+    }
+
+    procedures
+}
+
+/// The insert, update, and delete procedures for a collection of entities of the given type. Each
+/// procedure takes the given arguments as well as its own. We can't insert entities of an abstract
+/// type, and we leave out any procedure whose write the collection's restrictions disallow.
+fn collection_procedures(
+    metadata: &odata::EDMX,
+    collection: &str,
+    entity_type: &odata::QualifiedType,
+    arguments: &BTreeMap<String, ndc::Type>,
+    restrictions: &ndc::Restrictions,
+) -> Vec<ndc::Procedure> {
+    let result_type = ndc::Type::Qualified {
+        qualified_type: mutation_result_type(),
+    };
+
+    let predicate = ndc::Type::Qualified {
+        qualified_type: expression_type(),
+    };
+
+    let objects = ndc::Type::Collection {
+        element_type: Box::new(ndc::Type::Qualified {
+            qualified_type: insert_type(entity_type),
+        }),
+    };
+
+    let set = ndc::Type::Qualified {
+        qualified_type: update_type(entity_type),
+    };
+
+    let with_arguments = |own: Vec<(&str, ndc::Type)>| {
+        let mut all = arguments.clone();
+        all.extend(
+            own.into_iter()
+                .map(|(name, r#type)| (name.to_string(), r#type)),
+        );
+        all
+    };
+
+    let is_abstract = metadata
+        .entity_type(entity_type)
+        .is_some_and(|entity_type| entity_type.is_abstract);
+
+    let procedures = Vec::from([
+        ndc::Procedure {
+            name: format!("insert_{}_many", collection),
+            arguments: with_arguments(vec![("objects", objects)]),
+            result_type: result_type.clone(),
+            operation: ndc::Operation::InsertMany {
+                collection: collection.to_string(),
+            },
+            description: None,
+            argument_descriptions: BTreeMap::new(),
+            argument_constraints: BTreeMap::new(),
+        },
+        ndc::Procedure {
+            name: format!("update_{}_many", collection),
+            arguments: with_arguments(vec![("predicate", predicate.clone()), ("set", set)]),
+            result_type: result_type.clone(),
+            operation: ndc::Operation::UpdateMany {
+                collection: collection.to_string(),
+            },
+            description: None,
+            argument_descriptions: BTreeMap::new(),
+            argument_constraints: BTreeMap::new(),
+        },
+        ndc::Procedure {
+            name: format!("delete_{}_many", collection),
+            arguments: with_arguments(vec![("predicate", predicate)]),
+            result_type,
+            operation: ndc::Operation::DeleteMany {
+                collection: collection.to_string(),
+            },
+            description: None,
+            argument_descriptions: BTreeMap::new(),
+            argument_constraints: BTreeMap::new(),
+        },
+    ]);
+
+    procedures
+        .into_iter()
+        .filter(|procedure| match procedure.operation {
+            ndc::Operation::InsertMany { .. } => !is_abstract && restrictions.insertable,
+            ndc::Operation::UpdateMany { .. } => restrictions.updatable,
+            ndc::Operation::DeleteMany { .. } => restrictions.deletable,
+            _ => true,
+        })
+        .collect()
+}
+
+/// The contained collections among the given collections, along with their entity types.
+fn contained_collections(
+    collections: &[ndc::Collection],
+) -> Vec<(&ndc::Collection, odata::QualifiedType)> {
+    collections
+        .iter()
+        .filter(|collection| collection.containment.is_some())
+        .map(|collection| {
+            let entity_type = odata::QualifiedType {
+                schema: collection.collection_type.schema.clone(),
+                name: collection.collection_type.name.clone(),
+            };
+
+            (collection, entity_type)
+        })
+        .collect()
+}
+
+/// The procedures for linking and unlinking the entities related through each navigation property
+/// binding of an entity set. Collection-valued navigation properties get `link_` and `unlink_`
+/// procedures, and single-valued navigation properties get a `set_` procedure. We leave out the
+/// navigation properties that the service won't let us navigate or update.
+fn reference_procedures(
+    metadata: &odata::EDMX,
+    schema: &odata::Schema,
+    entity_set: &odata::EntitySet,
+    restrictions: &ndc::Restrictions,
+) -> Vec<ndc::Procedure> {
+    let mut procedures = Vec::new();
+
+    let entity_type = match metadata.entity_type(&entity_set.entity_type) {
+        Some(entity_type) => entity_type,
+        None => return procedures,
+    };
+
+    let key = ndc::keys::key_type(metadata, &entity_set.entity_type);
+
+    let result_type = ndc::Type::Qualified {
+        qualified_type: mutation_result_type(),
+    };
+
+    for binding in &entity_set.navigation_property_bindings {
+        if !restrictions.can_link(&binding.path) {
+            continue;
+        }
+
+        let navigation_property = match entity_type.navigation_property(metadata, &binding.path) {
+            Some(navigation_property) => navigation_property,
+            None => continue,
+        };
+
+        let target_key = match metadata.binding_target(schema, &binding.target) {
+            Some(target) => ndc::keys::key_type(metadata, &target.entity_type),
+            None => continue,
+        };
+
+        // Binding paths can include type casts, so we tidy them up to make a valid name.
+        let suffix = format!(
+            "{}_{}",
+            entity_set.name,
+            binding.path.replace(['.', '/'], "_")
+        );
+
+        let collection = entity_set.name.clone();
+        let navigation = binding.path.clone();
+
+        if navigation_property.is_collection() {
+            let arguments = BTreeMap::from([
+                ("key".to_string(), key.clone()),
+                ("target_key".to_string(), target_key),
+            ]);
+
+            procedures.push(ndc::Procedure {
+                name: format!("link_{suffix}"),
+                arguments: arguments.clone(),
+                result_type: result_type.clone(),
+                operation: ndc::Operation::Link {
+                    collection: collection.clone(),
+                    navigation: navigation.clone(),
+                },
+                description: None,
+                argument_descriptions: BTreeMap::new(),
+                argument_constraints: BTreeMap::new(),
+            });
+
+            procedures.push(ndc::Procedure {
+                name: format!("unlink_{suffix}"),
+                arguments,
+                result_type: result_type.clone(),
+                operation: ndc::Operation::Unlink {
+                    collection,
+                    navigation,
+                },
+                description: None,
+                argument_descriptions: BTreeMap::new(),
+                argument_constraints: BTreeMap::new(),
+            });
+        } else {
+            let arguments = BTreeMap::from([
+                ("key".to_string(), key.clone()),
+                ("target_key".to_string(), target_key.optional()),
+            ]);
+
+            procedures.push(ndc::Procedure {
+                name: format!("set_{suffix}"),
+                arguments,
+                result_type: result_type.clone(),
+                operation: ndc::Operation::SetLink {
+                    collection,
+                    navigation,
+                },
+                description: None,
+                argument_descriptions: BTreeMap::new(),
+                argument_constraints: BTreeMap::new(),
+            });
+        }
+    }
+
+    procedures
+}
@@ -7,12 +7,52 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
 /// In `ndc-odata`, we don't distinguish between entity types and complex types: the only
-/// difference is the presence of a key, and we represent them as foreign key relationships.
+/// difference is the presence of a key. Navigation properties aren't fields at all: we expose them
+/// as relationships between collections.
 /// However, because this distinction only exists within `ndc-odata`, we must track them
 /// separately.
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct ObjectType {
-    pub fields: BTreeMap<String, super::Type>,
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The object type that this one extends, if any. We've already copied the fields it declares
+    /// into this one, so we only keep its name to check the hierarchy.
+    #[serde(default)]
+    pub base_type: Option<String>,
+
+    pub fields: BTreeMap<String, ObjectField>,
+}
+
+/// A field of an object type, which is a property of the underlying entity type or complex type.
+/// The type is flattened into the field, so a field without any of the optional details looks
+/// just like a bare type.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct ObjectField {
+    #[serde(flatten)]
+    pub r#type: super::Type,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The values that the field accepts, if the metadata constrains them.
+    #[serde(default)]
+    pub constraints: Option<super::Constraints>,
+
+    /// If clients can't write the field, the reason why.
+    #[serde(default)]
+    pub read_only: Option<super::ReadOnly>,
+}
+
+impl From<super::Type> for ObjectField {
+    fn from(r#type: super::Type) -> Self {
+        ObjectField {
+            r#type,
+            description: None,
+            constraints: None,
+            read_only: None,
+        }
+    }
 }
 
 impl ObjectType {
@@ -24,30 +64,58 @@ impl ObjectType {
         let mut object_types = BTreeMap::new();
 
         for entity_type in &schema.entity_types {
-            let object_type = odata::Type::Qualified {
-                qualified_type: odata::QualifiedType {
-                    schema: schema.namespace.clone(),
-                    name: entity_type.name.clone(),
-                },
+            let qualified_type = odata::QualifiedType {
+                schema: schema.namespace.clone(),
+                name: entity_type.name.clone(),
             };
 
-            object_types.insert(
-                object_type.to_string(),
-                from_entity_type(metadata, entity_type),
-            );
+            let mut object_type = from_entity_type(metadata, &qualified_type, entity_type);
+
+            // Entities in a hierarchy may be of any type within it, so we say which.
+            if super::derived_types::is_polymorphic(metadata, &qualified_type) {
+                let discriminator = super::Type::Qualified {
+                    qualified_type: super::derived_types::type_name_type(),
+                };
+
+                object_type.fields.insert(
+                    super::derived_types::DISCRIMINATOR.to_string(),
+                    ObjectField {
+                        r#type: discriminator,
+                        description: Some("The entity type of this entity.".to_string()),
+                        constraints: None,
+                        read_only: None,
+                    },
+                );
+            }
+
+            // Anything the metadata doesn't declare, we collect into a single JSON column.
+            if entity_type.is_open {
+                object_type.fields.insert(
+                    super::open_types::DYNAMIC_PROPERTIES.to_string(),
+                    ObjectField {
+                        r#type: super::open_types::dynamic_properties_type(),
+                        description: Some(
+                            "The properties of this entity that its type doesn't declare."
+                                .to_string(),
+                        ),
+                        constraints: None,
+                        read_only: None,
+                    },
+                );
+            }
+
+            object_types.insert(qualified_type.to_string(), object_type);
         }
 
         for complex_type in &schema.complex_types {
-            let object_type = odata::Type::Qualified {
-                qualified_type: odata::QualifiedType {
-                    schema: schema.namespace.clone(),
-                    name: complex_type.name.clone(),
-                },
+            let qualified_type = odata::QualifiedType {
+                schema: schema.namespace.clone(),
+                name: complex_type.name.clone(),
             };
 
             object_types.insert(
-                object_type.to_string(),
-                from_complex_type(metadata, complex_type),
+                qualified_type.to_string(),
+                from_complex_type(metadata, &qualified_type, complex_type),
             );
         }
 
@@ -55,73 +123,66 @@ impl ObjectType {
     }
 }
 
-/// Create an object type based on a complex type underneath.
-fn from_complex_type(metadata: &odata::EDMX, structure: &odata::ComplexType) -> ObjectType {
-    let mut fields = BTreeMap::new();
-
-    if let Some(base_type) = &structure.base_type {
-        match metadata.complex_type(base_type) {
-            Some(complex_type) => {
-                let mut inner = from_complex_type(metadata, &complex_type);
-                fields.append(&mut inner.fields);
-            }
-            None => panic!("{}'s base type {} doesn't exist", structure.name, base_type),
-        }
-    }
-
-    for property in &structure.properties {
-        let name = property.name.clone();
-        let value = super::Type::from_type_data(&property.r#type);
-
-        fields.insert(name, value);
-    }
-
-    let mut navigation = navigation_properties(metadata, &structure.navigation_properties);
-    fields.append(&mut navigation);
-
-    ObjectType { fields }
+/// Create an object type based on a complex type underneath, including the fields it inherits.
+fn from_complex_type(
+    metadata: &odata::EDMX,
+    qualified_type: &odata::QualifiedType,
+    structure: &odata::ComplexType,
+) -> ObjectType {
+    let base_type = structure.base_type.as_ref().map(ToString::to_string);
+    from_properties(
+        metadata,
+        qualified_type,
+        base_type,
+        structure.fields(metadata),
+    )
 }
 
-/// Create an object type based on an entity type underneath.
-fn from_entity_type(metadata: &odata::EDMX, structure: &odata::EntityType) -> ObjectType {
-    let mut fields = BTreeMap::new();
-
-    for property in &structure.properties {
-        let name = property.name.clone();
-        let value = super::Type::from_type_data(&property.r#type);
-
-        fields.insert(name, value);
-    }
-
-    let mut navigation =
-        navigation_properties(metadata, &structure.navigation_properties(metadata));
-    fields.append(&mut navigation);
-
-    ObjectType { fields }
+/// Create an object type based on an entity type underneath, including the fields it inherits.
+fn from_entity_type(
+    metadata: &odata::EDMX,
+    qualified_type: &odata::QualifiedType,
+    structure: &odata::EntityType,
+) -> ObjectType {
+    let base_type = structure.base_type.as_ref().map(ToString::to_string);
+    from_properties(
+        metadata,
+        qualified_type,
+        base_type,
+        structure.fields(metadata),
+    )
 }
 
-/// Convert OData navigation properties to `ndc-odata` navigation properties.
-fn navigation_properties(
+fn from_properties(
     metadata: &odata::EDMX,
-    navigation_properties: &Vec<odata::NavigationProperty>,
-) -> BTreeMap<String, super::Type> {
+    qualified_type: &odata::QualifiedType,
+    base_type: Option<String>,
+    properties: Vec<odata::Property>,
+) -> ObjectType {
+    let mut descriptions = super::descriptions::properties(metadata, qualified_type);
+    let mut constraints = super::constraints::properties(metadata, qualified_type);
+    let read_only = super::read_only::properties(metadata, qualified_type);
     let mut fields = BTreeMap::new();
 
-    for navigation_property in navigation_properties {
-        let target_type = navigation_property.r#type.inner.underlying_type();
-        let target = metadata.entity_type(target_type).clone().unwrap();
-
-        let qualified_type = target.key_type(metadata);
+    for property in properties {
+        let r#type = super::Type::from_type_data(&property.r#type);
+        let description = descriptions.remove(&property.name);
+        let constraints = constraints.remove(&property.name);
+        let read_only = read_only.get(&property.name).copied();
         fields.insert(
-            navigation_property.name.clone(),
-            super::Type::Qualified {
-                qualified_type: super::QualifiedType {
-                    schema: qualified_type.schema.clone(),
-                    name: qualified_type.name.clone(),
-                },
+            property.name,
+            ObjectField {
+                r#type,
+                description,
+                constraints,
+                read_only,
             },
         );
     }
 
-    fields
+    ObjectType {
+        description: super::descriptions::structured_type(metadata, qualified_type),
+        base_type,
+        fields,
+    }
 }
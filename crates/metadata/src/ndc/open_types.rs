@@ -0,0 +1,60 @@
+//! Open entity types may return properties that the metadata doesn't declare. We collect these
+//! dynamic properties into a single JSON column. If callers want to select particular dynamic
+//! properties by name, the configuration can declare them, along with their types.
+//!
+//! Open complex types need none of this: we return complex values whole, dynamic properties and
+//! all.
+
+use crate::ndc;
+use crate::odata;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The column that holds every property of an entity that its type doesn't declare.
+pub const DYNAMIC_PROPERTIES: &str = "__dynamic";
+
+/// The dynamic properties column is an object of arbitrary JSON values.
+pub fn dynamic_properties_type() -> ndc::Type {
+    ndc::Type::Qualified {
+        qualified_type: ndc::types::json_type(),
+    }
+}
+
+/// The scalar types that the dynamic properties column needs.
+pub fn scalar_types(schema: &odata::Schema) -> BTreeSet<ndc::ScalarType> {
+    let mut scalar_types = BTreeSet::new();
+
+    if schema
+        .entity_types
+        .iter()
+        .any(|entity_type| entity_type.is_open)
+    {
+        scalar_types.insert(ndc::ScalarType(ndc::types::json_type().to_string()));
+    }
+
+    scalar_types
+}
+
+/// Add the configured dynamic properties of an open entity type to its object type. Any entity
+/// may leave out any dynamic property, so they're all nullable.
+pub fn declare(
+    schema: &mut ndc::Schema,
+    object_type: &str,
+    dynamic_properties: &BTreeMap<String, ndc::Type>,
+) -> Result<(), String> {
+    let object_type = match schema.object_types.get_mut(object_type) {
+        Some(candidate) if candidate.fields.contains_key(DYNAMIC_PROPERTIES) => candidate,
+        _ => return Err(format!("{object_type} isn't an open entity type.")),
+    };
+
+    for (name, property_type) in dynamic_properties {
+        if object_type.fields.contains_key(name) {
+            return Err(format!("{name} is already a declared property."));
+        }
+
+        object_type
+            .fields
+            .insert(name.clone(), property_type.clone().optional().into());
+    }
+
+    Ok(())
+}
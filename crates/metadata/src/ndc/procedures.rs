@@ -1,7 +1,7 @@
 //! Functions relating to extracting procedures from OData metadata.
 
 use crate::ndc;
-use crate::odata::{functions, schema};
+use crate::odata::{self, functions, schema};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -12,29 +12,168 @@ pub struct Procedure {
     pub name: String,
     pub arguments: BTreeMap<String, ndc::Type>,
     pub result_type: ndc::Type,
+    pub operation: Operation,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub argument_descriptions: BTreeMap<String, String>,
+
+    /// The values that each argument accepts, if the metadata constrains them.
+    #[serde(default)]
+    pub argument_constraints: BTreeMap<String, ndc::Constraints>,
+}
+
+/// What a procedure actually does when it is called. Some procedures are direct translations of
+/// OData actions, but others are generated by the connector to give us writes against entity sets.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "type")]
+pub enum Operation {
+    /// Invoke the named OData action, on the entities identified by the binding if it is bound.
+    Action {
+        action: String,
+
+        #[serde(default)]
+        binding: Option<super::Binding>,
+    },
+
+    /// Insert every object in the `objects` argument into the collection.
+    InsertMany { collection: String },
+
+    /// Apply the `set` argument to every entity in the collection matching the `predicate`.
+    UpdateMany { collection: String },
+
+    /// Delete every entity in the collection matching the `predicate`.
+    DeleteMany { collection: String },
+
+    /// Apply the `set` argument to the singleton.
+    UpdateSingleton { singleton: String },
+
+    /// Add the entity identified by `target_key` to a collection-valued navigation property of the
+    /// entity identified by `key`.
+    Link {
+        collection: String,
+        navigation: String,
+    },
+
+    /// Remove the entity identified by `target_key` from a collection-valued navigation property
+    /// of the entity identified by `key`.
+    Unlink {
+        collection: String,
+        navigation: String,
+    },
+
+    /// Point a single-valued navigation property of the entity identified by `key` at the entity
+    /// identified by `target_key`, or clear it if `target_key` is null.
+    SetLink {
+        collection: String,
+        navigation: String,
+    },
 }
 
 impl Procedure {
-    pub fn extract_from(schema: &schema::Schema) -> Vec<ndc::Procedure> {
+    pub fn extract_from(metadata: &odata::EDMX, schema: &schema::Schema) -> Vec<ndc::Procedure> {
         let mut procedures = Vec::new();
 
-        for action in &schema.actions {
-            if let Some(return_type) = &action.return_type {
-                let mut arguments = BTreeMap::new();
-                let result_type = super::types::Type::from_type_data(return_type);
+        // Unbound actions are only available through the action imports of the entity container,
+        // and they're invoked by the import's name rather than the action's.
+        for action_import in &schema.entity_container.action_imports {
+            let action = match metadata.unbound_action(&action_import.action) {
+                Some(action) => action,
+                None => continue,
+            };
+
+            // The import's own documentation takes precedence over the action's.
+            let description = ndc::descriptions::container_child(
+                metadata,
+                schema,
+                &action_import.name,
+                &action_import.annotations,
+            )
+            .or_else(|| {
+                let target = &action_import.action;
+                ndc::descriptions::describe(metadata, target, &action.annotations)
+            });
+
+            procedures.push(ndc::Procedure {
+                name: action_import.name.clone(),
+                arguments: Self::arguments(&action),
+                result_type: Self::result_type(&action),
+                operation: Operation::Action {
+                    action: action_import.name.clone(),
+                    binding: None,
+                },
+                description,
+                argument_descriptions: ndc::descriptions::parameters(
+                    metadata,
+                    &action_import.action,
+                    &action.parameters,
+                ),
+                argument_constraints: ndc::constraints::parameters(
+                    metadata,
+                    &action_import.action,
+                    &action.parameters,
+                ),
+            });
+        }
 
-                for functions::Parameter { name, r#type } in &action.parameters {
-                    arguments.insert(name.clone(), ndc::types::Type::from_type_data(r#type));
-                }
+        // Like bound functions, bound actions are exposed once for each entity set they can be
+        // bound to. Bound actions can only be overloaded by binding parameter type, so the
+        // collection prefix is enough to tell the overloads apart.
+        for action in schema.actions.iter().filter(|action| action.is_bound) {
+            let qualified_name = format!("{}.{}", schema.namespace, action.name);
+            let description =
+                ndc::descriptions::describe(metadata, &qualified_name, &action.annotations);
+            let argument_descriptions =
+                ndc::descriptions::parameters(metadata, &qualified_name, &action.parameters);
+            let argument_constraints =
+                ndc::constraints::parameters(metadata, &qualified_name, &action.parameters);
+
+            for (binding, argument_type) in
+                ndc::Binding::extract_from(metadata, schema, &action.name, &action.parameters)
+            {
+                let mut arguments = Self::arguments(action);
+                arguments.insert(binding.parameter.clone(), argument_type);
 
                 procedures.push(ndc::Procedure {
-                    name: action.name.clone(),
+                    name: format!("{}_{}", binding.collection, action.name),
                     arguments,
-                    result_type,
+                    result_type: Self::result_type(action),
+                    operation: Operation::Action {
+                        action: action.name.clone(),
+                        binding: Some(binding),
+                    },
+                    description: description.clone(),
+                    argument_descriptions: argument_descriptions.clone(),
+                    argument_constraints: argument_constraints.clone(),
                 });
             }
         }
 
         procedures
     }
+
+    /// The result type of an action's procedure. Actions without a return type respond with
+    /// `204 No Content`, but GraphQL mutations must return something, so we give them the same
+    /// result type as the generated procedures.
+    fn result_type(action: &odata::Action) -> ndc::Type {
+        match &action.return_type {
+            Some(return_type) => ndc::Type::from_type_data(return_type),
+            None => ndc::Type::Qualified {
+                qualified_type: ndc::mutations::mutation_result_type(),
+            },
+        }
+    }
+
+    /// The NDC arguments for each of an action's parameters.
+    fn arguments(action: &odata::Action) -> BTreeMap<String, ndc::Type> {
+        let mut arguments = BTreeMap::new();
+
+        for functions::Parameter { name, r#type, .. } in &action.parameters {
+            arguments.insert(name.clone(), ndc::types::Type::from_type_data(r#type));
+        }
+
+        arguments
+    }
 }
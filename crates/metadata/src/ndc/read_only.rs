@@ -0,0 +1,71 @@
+//! Some properties can be read but not written: the service computes them, fixes them when the
+//! entity is created, or simply doesn't allow changes. The Core vocabulary tells us which, and we
+//! leave these properties out of the input types of the generated procedures so that clients can't
+//! attempt writes that the service will reject.
+
+use crate::odata;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const CORE: &str = "Org.OData.Core.V1";
+
+/// Why clients can't write a field.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum ReadOnly {
+    /// The service computes the value (`Core.Computed`), as it does for server-generated keys.
+    Computed,
+
+    /// The value can't change once the entity exists (`Core.Immutable`).
+    Immutable,
+
+    /// The property's permissions only allow reading (`Core.Permissions`).
+    Permissions,
+}
+
+impl ReadOnly {
+    /// Read the Core annotations of a property, which may be written inline or out of line.
+    pub fn extract_from(
+        metadata: &odata::EDMX,
+        target: &str,
+        inline: &[odata::Annotation],
+    ) -> Option<Self> {
+        let annotations = metadata.applicable_annotations(target, inline);
+        let term = |name: &str| odata::find_term(&annotations, CORE, name);
+
+        if term("Computed").is_some_and(odata::Annotation::is_set) {
+            return Some(ReadOnly::Computed);
+        }
+
+        if term("Immutable").is_some_and(odata::Annotation::is_set) {
+            return Some(ReadOnly::Immutable);
+        }
+
+        let permissions = term("Permissions").map(odata::Annotation::enum_members)?;
+        let writable = permissions
+            .iter()
+            .any(|member| matches!(*member, "Write" | "ReadWrite"));
+
+        (!writable && permissions.contains(&"Read")).then_some(ReadOnly::Permissions)
+    }
+}
+
+/// Find the read-only properties of an entity type or complex type, including those it inherits.
+pub fn properties(
+    metadata: &odata::EDMX,
+    structured_type: &odata::QualifiedType,
+) -> BTreeMap<String, ReadOnly> {
+    let mut read_only = BTreeMap::new();
+
+    for (declaring_type, properties) in metadata.declaring_types(structured_type) {
+        for property in properties {
+            let target = format!("{declaring_type}/{}", property.name);
+
+            if let Some(found) = ReadOnly::extract_from(metadata, &target, &property.annotations) {
+                read_only.insert(property.name, found);
+            }
+        }
+    }
+
+    read_only
+}
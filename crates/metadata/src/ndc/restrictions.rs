@@ -0,0 +1,164 @@
+//! Services use the Capabilities vocabulary to say what each entity set doesn't support: perhaps
+//! it can't be filtered by some of its properties, or it can't be written to at all. We keep track
+//! of these restrictions so that we don't expose (or send) requests that the service will reject.
+
+use crate::odata;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+const CAPABILITIES: &str = "Org.OData.Capabilities.V1";
+
+/// What a collection supports. Without any annotations, a collection supports everything.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Restrictions {
+    pub filterable: bool,
+    pub non_filterable_properties: BTreeSet<String>,
+
+    pub sortable: bool,
+    pub non_sortable_properties: BTreeSet<String>,
+
+    pub countable: bool,
+
+    pub expandable: bool,
+    pub non_expandable_properties: BTreeSet<String>,
+
+    pub insertable: bool,
+    pub updatable: bool,
+    pub deletable: bool,
+
+    pub navigable: bool,
+    pub non_linkable_properties: BTreeSet<String>,
+}
+
+impl Default for Restrictions {
+    fn default() -> Self {
+        Restrictions {
+            filterable: true,
+            non_filterable_properties: BTreeSet::new(),
+            sortable: true,
+            non_sortable_properties: BTreeSet::new(),
+            countable: true,
+            expandable: true,
+            non_expandable_properties: BTreeSet::new(),
+            insertable: true,
+            updatable: true,
+            deletable: true,
+            navigable: true,
+            non_linkable_properties: BTreeSet::new(),
+        }
+    }
+}
+
+impl Restrictions {
+    /// Read the restrictions of an entity set from its annotations, which may be written inline or
+    /// out of line (targeting `Trippin.Container/People`).
+    pub fn extract_from(
+        metadata: &odata::EDMX,
+        schema: &odata::Schema,
+        entity_set: &odata::EntitySet,
+    ) -> Self {
+        let target = format!(
+            "{}.{}/{}",
+            schema.namespace, schema.entity_container.name, entity_set.name
+        );
+
+        let annotations = metadata.applicable_annotations(&target, &entity_set.annotations);
+
+        let record = |name: &str| {
+            odata::find_term(&annotations, CAPABILITIES, name)
+                .and_then(|annotation| annotation.record.as_ref())
+        };
+
+        let flag = |restriction: &str, property: &str| {
+            record(restriction)
+                .and_then(|record| record.property(property))
+                .and_then(odata::PropertyValue::bool)
+                .unwrap_or(true)
+        };
+
+        let paths = |restriction: &str, property: &str| {
+            record(restriction)
+                .and_then(|record| record.property(property))
+                .map(|property_value| property_value.paths().into_iter().collect())
+                .unwrap_or_default()
+        };
+
+        let navigation = record("NavigationRestrictions");
+
+        let navigable = |record: &odata::Record| {
+            record
+                .property("Navigability")
+                .and_then(odata::PropertyValue::enum_member)
+                != Some("None")
+        };
+
+        let navigable_by_default = navigation.is_none_or(navigable);
+
+        // Each restricted property can override the collection's navigability, and can also be
+        // read-only in its own right.
+        let restricted_properties = navigation
+            .and_then(|record| record.property("RestrictedProperties"))
+            .map(odata::PropertyValue::records)
+            .unwrap_or_default();
+
+        let non_linkable_properties: BTreeSet<String> = restricted_properties
+            .iter()
+            .filter(|restricted| {
+                let updatable = restricted
+                    .property("UpdateRestrictions")
+                    .and_then(|property_value| property_value.record.as_ref())
+                    .and_then(|record| record.property("Updatable"))
+                    .and_then(odata::PropertyValue::bool)
+                    .unwrap_or(true);
+
+                let navigable = match restricted.property("Navigability") {
+                    Some(_) => navigable(restricted),
+                    None => navigable_by_default,
+                };
+
+                !(updatable && navigable)
+            })
+            .filter_map(|restricted| restricted.property("NavigationProperty"))
+            .filter_map(odata::PropertyValue::navigation_property_path)
+            .map(String::from)
+            .collect();
+
+        Restrictions {
+            filterable: flag("FilterRestrictions", "Filterable"),
+            non_filterable_properties: paths("FilterRestrictions", "NonFilterableProperties"),
+            sortable: flag("SortRestrictions", "Sortable"),
+            non_sortable_properties: paths("SortRestrictions", "NonSortableProperties"),
+            countable: flag("CountRestrictions", "Countable"),
+            expandable: flag("ExpandRestrictions", "Expandable"),
+            non_expandable_properties: paths("ExpandRestrictions", "NonExpandableProperties"),
+            insertable: flag("InsertRestrictions", "Insertable"),
+            updatable: flag("UpdateRestrictions", "Updatable"),
+            deletable: flag("DeleteRestrictions", "Deletable"),
+            navigable: navigable_by_default,
+            non_linkable_properties,
+        }
+    }
+
+    /// Can we filter the collection by the given property?
+    pub fn can_filter_by(&self, property: &str) -> bool {
+        self.filterable && !self.non_filterable_properties.contains(property)
+    }
+
+    /// Can we sort the collection by the given property?
+    pub fn can_sort_by(&self, property: &str) -> bool {
+        self.sortable && !self.non_sortable_properties.contains(property)
+    }
+
+    /// Can we expand the given navigation property of the collection's entities?
+    pub fn can_expand(&self, navigation: &str) -> bool {
+        self.expandable && !self.non_expandable_properties.contains(navigation)
+    }
+
+    /// Can we change which entities the given navigation property of the collection's entities
+    /// leads to?
+    pub fn can_link(&self, navigation: &str) -> bool {
+        self.updatable && self.navigable && !self.non_linkable_properties.contains(navigation)
+    }
+}
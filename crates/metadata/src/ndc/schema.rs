@@ -1,11 +1,14 @@
 //! The core schema type.
 
+use crate::odata;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Clone, Debug, Default, Deserialize, JsonSchema, Serialize)]
 pub struct Schema {
+    #[serde(default)]
+    pub version: Version,
     #[serde(default)]
     pub collections: Vec<super::Collection>,
     #[serde(default)]
@@ -13,6 +16,8 @@ pub struct Schema {
     #[serde(default)]
     pub object_types: BTreeMap<String, super::ObjectType>,
     #[serde(default)]
+    pub enum_types: BTreeMap<String, super::EnumType>,
+    #[serde(default)]
     pub functions: Vec<super::Function>,
     #[serde(default)]
     pub procedures: Vec<super::Procedure>,
@@ -38,3 +43,24 @@ impl Schema {
             .find(|procedure| procedure.name == name)
     }
 }
+
+/// The version of the OData protocol that the service implements. We keep hold of this because
+/// some requests - set-based updates and deletes, or delta payloads - only exist from 4.01.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+pub enum Version {
+    #[default]
+    #[serde(rename = "4.0")]
+    V40,
+
+    #[serde(rename = "4.01")]
+    V401,
+}
+
+impl From<&odata::Version> for Version {
+    fn from(version: &odata::Version) -> Self {
+        match version.as_str() {
+            "4.01" => Version::V401,
+            _ => Version::V40,
+        }
+    }
+}
@@ -21,24 +21,51 @@ impl ScalarType {
         //  traversing the API schema.
         let mut scalar_types = BTreeSet::new();
 
-        for entity_type in &schema.entity_types {
-            for property in &entity_type.properties {
-                let underlying_type = property.underlying_type().to_string();
-
-                if PRIMITIVE_TYPES.contains(&underlying_type.as_str()) {
-                    scalar_types.insert(ScalarType(underlying_type));
-                }
+        // The parameters and return types of operations are as much a part of the API as the
+        // properties of its types.
+        let operations = schema
+            .functions
+            .iter()
+            .flat_map(|function| {
+                let parameters = function.parameters.iter().map(|x| &x.r#type);
+                parameters.chain([&function.return_type])
+            })
+            .chain(schema.actions.iter().flat_map(|action| {
+                let parameters = action.parameters.iter().map(|x| &x.r#type);
+                parameters.chain(action.return_type.as_ref())
+            }))
+            .map(types::TypeData::underlying_type);
+
+        let underlying_types = schema
+            .entity_types
+            .iter()
+            .flat_map(|entity_type| &entity_type.properties)
+            .chain(
+                schema
+                    .complex_types
+                    .iter()
+                    .flat_map(|complex_type| &complex_type.properties),
+            )
+            .map(|property| property.underlying_type())
+            .chain(operations);
+
+        for underlying_type in underlying_types {
+            let underlying_type = underlying_type.to_string();
+
+            if PRIMITIVE_TYPES.contains(&underlying_type.as_str()) {
+                scalar_types.insert(ScalarType(underlying_type));
+            } else if ABSTRACT_TYPES.contains(&underlying_type.as_str()) {
+                scalar_types.insert(ScalarType(json_type().to_string()));
             }
         }
 
-        for complex_type in &schema.complex_types {
-            for property in &complex_type.properties {
-                let underlying_type = property.underlying_type().to_string();
-
-                if PRIMITIVE_TYPES.contains(&underlying_type.as_str()) {
-                    scalar_types.insert(ScalarType(underlying_type));
-                }
-            }
+        // Enum types are scalars too, and we declare them all: they only exist to be used by
+        // the properties of the schema.
+        for enum_type in &schema.enum_types {
+            scalar_types.insert(ScalarType(format!(
+                "{}.{}",
+                schema.namespace, enum_type.name
+            )));
         }
 
         scalar_types
@@ -56,7 +83,7 @@ pub enum Type {
 }
 
 /// Qualified types that state both the schema and name of the type to which they refer.
-#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
 pub struct QualifiedType {
     pub schema: String,
     pub name: String,
@@ -68,7 +95,45 @@ impl std::fmt::Display for QualifiedType {
     }
 }
 
+impl From<&types::QualifiedType> for QualifiedType {
+    fn from(qualified_type: &types::QualifiedType) -> Self {
+        QualifiedType {
+            schema: qualified_type.schema.clone(),
+            name: qualified_type.name.clone(),
+        }
+    }
+}
+
 impl Type {
+    /// Find the named type underneath any collection and nullability wrappers.
+    pub fn underlying_type(&self) -> &QualifiedType {
+        match self {
+            Type::Collection { element_type } => element_type.underlying_type(),
+            Type::Nullable { underlying_type } => underlying_type.underlying_type(),
+            Type::Qualified { qualified_type } => qualified_type,
+        }
+    }
+
+    /// Is this a collection type, ignoring nullability?
+    pub fn is_collection(&self) -> bool {
+        match self {
+            Type::Collection { .. } => true,
+            Type::Nullable { underlying_type } => underlying_type.is_collection(),
+            Type::Qualified { .. } => false,
+        }
+    }
+
+    /// Make a type nullable, unless it already is. We use this for inputs in which every field
+    /// is optional, such as the changes to apply in an update.
+    pub fn optional(self) -> Self {
+        match self {
+            Type::Nullable { .. } => self,
+            _ => Type::Nullable {
+                underlying_type: Box::new(self),
+            },
+        }
+    }
+
     /// OData has a slightly different language for types (for example, you can't have a nullable array
     /// of nullable elements: all array elements are non-null), so we have to do a sightly clunky
     /// mapping.
@@ -77,10 +142,12 @@ impl Type {
             types::TypeData {
                 inner,
                 nullable: false,
+                ..
             } => {
                 let underlying = Type::from_type_data(&types::TypeData {
                     inner: inner.clone(),
                     nullable: true,
+                    facets: types::Facets::default(),
                 });
 
                 Type::Nullable {
@@ -95,6 +162,7 @@ impl Type {
                 let underlying = types::TypeData {
                     inner: *elements.clone(),
                     nullable: false,
+                    facets: types::Facets::default(),
                 };
 
                 Type::Collection {
@@ -106,22 +174,30 @@ impl Type {
                 inner: types::Type::Qualified { qualified_type },
                 ..
             } => Type::Qualified {
-                qualified_type: QualifiedType {
-                    schema: qualified_type.schema.clone(),
-                    name: qualified_type.name.clone(),
+                qualified_type: if ABSTRACT_TYPES.contains(&qualified_type.to_string().as_str()) {
+                    json_type()
+                } else {
+                    qualified_type.into()
                 },
             },
         }
     }
 }
 
+/// The scalar type that we use for values that could be any JSON at all: OData's abstract types,
+/// which stand in for any value of a given kind.
+pub fn json_type() -> QualifiedType {
+    QualifiedType {
+        schema: super::mutations::NAMESPACE.to_string(),
+        name: "JSON".to_string(),
+    }
+}
+
 /// All the primitive /scalar/ types. Rather than trying to deduce scalar types by whether
 /// or not they're defined elsewhere in the schema, we can make our lives a little easier by
 /// taking advantage of the fact that OData already has defined primitive types, and just
 /// filter out any types that don't show up in our schema.
-///
-/// @TODO: what do we do about geography and geometry?
-const PRIMITIVE_TYPES: [&str; 16] = [
+const PRIMITIVE_TYPES: [&str; 33] = [
     "Edm.Binary",
     "Edm.Boolean",
     "Edm.Byte",
@@ -130,12 +206,38 @@ const PRIMITIVE_TYPES: [&str; 16] = [
     "Edm.Decimal",
     "Edm.Double",
     "Edm.Duration",
+    "Edm.Geography",
+    "Edm.GeographyCollection",
+    "Edm.GeographyLineString",
+    "Edm.GeographyMultiLineString",
+    "Edm.GeographyMultiPoint",
+    "Edm.GeographyMultiPolygon",
+    "Edm.GeographyPoint",
+    "Edm.GeographyPolygon",
+    "Edm.Geometry",
+    "Edm.GeometryCollection",
+    "Edm.GeometryLineString",
+    "Edm.GeometryMultiLineString",
+    "Edm.GeometryMultiPoint",
+    "Edm.GeometryMultiPolygon",
+    "Edm.GeometryPoint",
+    "Edm.GeometryPolygon",
     "Edm.Guid",
     "Edm.Int16",
     "Edm.Int32",
     "Edm.Int64",
     "Edm.SByte",
     "Edm.Single",
+    "Edm.Stream",
     "Edm.String",
     "Edm.TimeOfDay",
 ];
+
+/// The abstract types, which can only be used in a few places (such as the properties of open or
+/// untyped structures). We can't say anything about their values, so they all become `OData.JSON`.
+const ABSTRACT_TYPES: [&str; 4] = [
+    "Edm.ComplexType",
+    "Edm.EntityType",
+    "Edm.PrimitiveType",
+    "Edm.Untyped",
+];
@@ -0,0 +1,303 @@
+//! Metadata can be broken in ways that the deserializer doesn't notice: an entity set might point
+//! at an entity type that doesn't exist, or a key at a property that doesn't exist. Rather than
+//! fail when a request happens to touch the broken part, we check the whole schema up front, both
+//! when we introspect it and when we load a configuration, and report every problem at once.
+
+use std::collections::BTreeSet;
+
+/// One step of a path into the configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyOrIndex {
+    Key(String),
+    Index(u32),
+}
+
+/// A problem with the configuration, along with where we found it.
+#[derive(Clone, Debug)]
+pub struct InvalidRange {
+    pub path: Vec<KeyOrIndex>,
+    pub message: String,
+}
+
+impl std::fmt::Display for InvalidRange {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, step) in self.path.iter().enumerate() {
+            match step {
+                KeyOrIndex::Key(key) if index == 0 => write!(formatter, "{}", key)?,
+                KeyOrIndex::Key(key) => write!(formatter, ".{}", key)?,
+                KeyOrIndex::Index(index) => write!(formatter, "[{}]", index)?,
+            }
+        }
+
+        write!(formatter, ": {}", self.message)
+    }
+}
+
+/// Check that everything the schema refers to exists:
+///
+/// - Every collection has an object type, and a key made of that type's fields.
+/// - Every base type exists, and no type is its own ancestor.
+/// - Every relationship targets a collection.
+/// - Every field, argument, and result has a declared scalar type or object type.
+///
+/// The paths of the problems we find start from the `schema` of the configuration.
+pub fn validate(schema: &super::Schema) -> Vec<InvalidRange> {
+    let mut invalid_ranges = Vec::new();
+
+    let collections: BTreeSet<&str> = schema
+        .collections
+        .iter()
+        .map(|collection| collection.name.as_str())
+        .collect();
+
+    for (index, collection) in schema.collections.iter().enumerate() {
+        let path = Path::from(["schema", "collections"]).index(index);
+        check_collection(schema, &collections, &path, collection, &mut invalid_ranges);
+    }
+
+    for (name, object_type) in &schema.object_types {
+        let path = Path::from(["schema", "object_types", name]);
+        check_base_type(schema, &path, name, object_type, &mut invalid_ranges);
+
+        for (field_name, field) in &object_type.fields {
+            let path = path.key("fields").key(field_name).key("type");
+            check_type(schema, &path, &field.r#type, &mut invalid_ranges);
+        }
+    }
+
+    for (index, function) in schema.functions.iter().enumerate() {
+        let path = Path::from(["schema", "functions"]).index(index);
+        check_type(
+            schema,
+            &path.key("result_type"),
+            &function.result_type,
+            &mut invalid_ranges,
+        );
+
+        for (name, argument) in &function.arguments {
+            let path = path.key("arguments").key(name);
+            check_type(schema, &path, argument, &mut invalid_ranges);
+        }
+    }
+
+    for (index, procedure) in schema.procedures.iter().enumerate() {
+        let path = Path::from(["schema", "procedures"]).index(index);
+        check_type(
+            schema,
+            &path.key("result_type"),
+            &procedure.result_type,
+            &mut invalid_ranges,
+        );
+
+        for (name, argument) in &procedure.arguments {
+            let path = path.key("arguments").key(name);
+            check_type(schema, &path, argument, &mut invalid_ranges);
+        }
+    }
+
+    invalid_ranges
+}
+
+fn check_collection(
+    schema: &super::Schema,
+    collections: &BTreeSet<&str>,
+    path: &Path,
+    collection: &super::Collection,
+    invalid_ranges: &mut Vec<InvalidRange>,
+) {
+    let collection_type = collection.collection_type.to_string();
+
+    match schema.object_types.get(&collection_type) {
+        Some(_) if collection.key.is_empty() => {
+            invalid_ranges.push(path.key("key").invalid(format!(
+                "Collection {} has no key, and {} doesn't inherit one.",
+                collection.name, collection_type
+            )))
+        }
+
+        Some(object_type) => {
+            for (index, key) in collection.key.iter().enumerate() {
+                if !has_field(schema, object_type, key.path()) {
+                    invalid_ranges.push(path.key("key").index(index).invalid(format!(
+                        "Key {} of collection {} isn't a property of {}.",
+                        key.path(),
+                        collection.name,
+                        collection_type
+                    )));
+                }
+            }
+        }
+
+        None => invalid_ranges.push(path.key("collection_type").invalid(format!(
+            "Collection {} has the type {}, which doesn't exist.",
+            collection.name, collection_type
+        ))),
+    }
+
+    for (navigation, target) in &collection.relationships {
+        if !collections.contains(target.as_str()) {
+            invalid_ranges.push(path.key("relationships").key(navigation).invalid(format!(
+                "Navigation property {} of collection {} is bound to {}, which isn't a collection.",
+                navigation, collection.name, target
+            )));
+        }
+    }
+
+    for (name, argument) in &collection.arguments {
+        let path = path.key("arguments").key(name);
+        check_type(schema, &path, argument, invalid_ranges);
+    }
+
+    if let Some(containment) = &collection.containment {
+        if !collections.contains(containment.parent.as_str()) {
+            invalid_ranges.push(path.key("containment").key("parent").invalid(format!(
+                "Collection {} is contained in {}, which isn't a collection.",
+                collection.name, containment.parent
+            )));
+        }
+    }
+
+    if let Some(type_cast) = &collection.type_cast {
+        if !collections.contains(type_cast.collection.as_str()) {
+            invalid_ranges.push(path.key("type_cast").key("collection").invalid(format!(
+                "Collection {} is a type cast of {}, which isn't a collection.",
+                collection.name, type_cast.collection
+            )));
+        }
+    }
+}
+
+/// Check that an object type's base type exists, and that following the chain of base types doesn't
+/// lead back to the object type itself: its fields would be its own ancestor's.
+fn check_base_type(
+    schema: &super::Schema,
+    path: &Path,
+    name: &str,
+    object_type: &super::ObjectType,
+    invalid_ranges: &mut Vec<InvalidRange>,
+) {
+    let path = path.key("base_type");
+
+    let Some(base_type) = object_type.base_type.as_deref() else {
+        return;
+    };
+
+    if !schema.object_types.contains_key(base_type) {
+        invalid_ranges.push(path.invalid(format!(
+            "The base type {} of {} doesn't exist.",
+            base_type, name
+        )));
+
+        return;
+    }
+
+    // Missing types and cycles further up the chain are reported against the types that cause
+    // them, so we only need to know whether we come back to this one.
+    let mut visited = BTreeSet::new();
+    let mut current = Some(base_type);
+
+    while let Some(ancestor) = current {
+        if ancestor == name {
+            invalid_ranges.push(path.invalid(format!(
+                "{} inherits from itself through {}.",
+                name, base_type
+            )));
+
+            return;
+        }
+
+        if !visited.insert(ancestor) {
+            return;
+        }
+
+        current = schema
+            .object_types
+            .get(ancestor)
+            .and_then(|ancestor| ancestor.base_type.as_deref());
+    }
+}
+
+/// Find a field by its path: `Address/City` is the `City` field of the object in the `Address`
+/// field.
+fn has_field(schema: &super::Schema, object_type: &super::ObjectType, path: &str) -> bool {
+    let mut object_type = object_type;
+    let mut segments = path.split('/').peekable();
+
+    while let Some(segment) = segments.next() {
+        let field = match object_type.fields.get(segment) {
+            Some(field) => field,
+            None => return false,
+        };
+
+        if segments.peek().is_none() {
+            return true;
+        }
+
+        let field_type = field.r#type.underlying_type().to_string();
+
+        object_type = match schema.object_types.get(&field_type) {
+            Some(object_type) => object_type,
+            None => return false,
+        };
+    }
+
+    false
+}
+
+/// Check that a type refers to a declared scalar type or object type. Enum types are declared as
+/// scalar types, too.
+fn check_type(
+    schema: &super::Schema,
+    path: &Path,
+    r#type: &super::Type,
+    invalid_ranges: &mut Vec<InvalidRange>,
+) {
+    let underlying_type = r#type.underlying_type().to_string();
+
+    let declared = schema.object_types.contains_key(&underlying_type)
+        || schema
+            .scalar_types
+            .contains(&super::ScalarType(underlying_type.clone()));
+
+    if !declared {
+        invalid_ranges.push(path.invalid(format!(
+            "The type {} isn't declared as a scalar type or an object type.",
+            underlying_type
+        )));
+    }
+}
+
+/// A path into the configuration, which we extend as we go deeper.
+#[derive(Clone)]
+struct Path(Vec<KeyOrIndex>);
+
+impl Path {
+    fn key(&self, key: &str) -> Path {
+        let mut path = self.0.clone();
+        path.push(KeyOrIndex::Key(key.to_string()));
+        Path(path)
+    }
+
+    fn index(&self, index: usize) -> Path {
+        let mut path = self.0.clone();
+        path.push(KeyOrIndex::Index(index as u32));
+        Path(path)
+    }
+
+    fn invalid(&self, message: String) -> InvalidRange {
+        InvalidRange {
+            path: self.0.clone(),
+            message,
+        }
+    }
+}
+
+impl<const N: usize> From<[&str; N]> for Path {
+    fn from(keys: [&str; N]) -> Self {
+        Path(
+            keys.iter()
+                .map(|key| KeyOrIndex::Key(key.to_string()))
+                .collect(),
+        )
+    }
+}
@@ -1,22 +1,28 @@
 //! A representation of (some of) the OData Common Schema Definition Language (CSDL).
 
 pub mod actions;
+pub mod annotations;
 pub mod entities;
 pub mod enums;
 pub mod functions;
 pub mod helpers;
+pub mod json;
+pub mod references;
 pub mod schema;
 pub mod types;
 
 pub use actions::*;
+pub use annotations::*;
 pub use entities::*;
 pub use enums::*;
 pub use functions::*;
 pub use helpers::*;
+pub use references::*;
 pub use schema::*;
 pub use types::*;
 
 use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, BTreeSet};
 
 /// The top-level tag of an OData schema.
 #[derive(Clone, Debug, Deserialize)]
@@ -27,15 +33,222 @@ pub struct EDMX {
     #[serde(rename = "@xmlns:edmx")]
     pub edmx: Option<String>,
 
+    #[serde(default)]
+    #[serde(rename = "Reference")]
+    pub references: Vec<Reference>,
+
     #[serde(rename = "DataServices")]
     pub data_services: DataServices,
 }
 
 impl EDMX {
+    /// Bring the schemas of a referenced document into this one, as long as the reference
+    /// includes them. We keep their containers only until we've resolved any container that
+    /// extends them: the service itself doesn't expose them.
+    pub fn include(&mut self, reference: &Reference, document: EDMX) {
+        for mut schema in document.data_services.schema {
+            let is_included = reference
+                .includes
+                .iter()
+                .any(|include| include.namespace == schema.namespace);
+
+            if is_included && self.schema(&schema.namespace).is_none() {
+                schema.is_referenced = true;
+                self.data_services.schema.push(schema);
+            }
+        }
+    }
+
+    /// Every alias in scope within this document, mapped to the namespace it stands for. Aliases
+    /// come from both the document's own schemas and the schemas it includes from references.
+    pub fn aliases(&self) -> BTreeMap<String, String> {
+        let included = self
+            .references
+            .iter()
+            .flat_map(|reference| reference.includes.iter())
+            .filter_map(|include| Some((include.alias.clone()?, include.namespace.clone())));
+
+        let own = self
+            .data_services
+            .schema
+            .iter()
+            .filter(|schema| !schema.is_referenced)
+            .filter_map(|schema| Some((schema.alias.clone()?, schema.namespace.clone())));
+
+        included.chain(own).collect()
+    }
+
+    /// Replace every aliased reference to a type, function, action, container, or annotation term
+    /// with its namespace-qualified name. After this, we can compare names without thinking about
+    /// aliases.
+    pub fn resolve_aliases(&mut self) {
+        let aliases = self.aliases();
+
+        if aliases.is_empty() {
+            return;
+        }
+
+        let unalias = |qualified_type: &mut QualifiedType| {
+            if let Some(namespace) = aliases.get(&qualified_type.schema) {
+                qualified_type.schema = namespace.clone();
+            }
+        };
+
+        // Aliases are simple identifiers, so they can't contain a period of their own.
+        let unalias_name = |name: &mut String| {
+            if let Some((alias, rest)) = name.split_once('.') {
+                if let Some(namespace) = aliases.get(alias) {
+                    *name = format!("{}.{}", namespace, rest);
+                }
+            }
+        };
+
+        for schema in &mut self.data_services.schema {
+            for type_data in schema.types_mut() {
+                let mut qualified_type = type_data.underlying_type().clone();
+                unalias(&mut qualified_type);
+                type_data.inner.replace_underlying_type(qualified_type);
+            }
+
+            for qualified_type in schema.qualified_types_mut() {
+                unalias(qualified_type);
+            }
+
+            let container = &mut schema.entity_container;
+
+            for function_import in &mut container.function_imports {
+                unalias_name(&mut function_import.function);
+            }
+
+            for action_import in &mut container.action_imports {
+                unalias_name(&mut action_import.action);
+            }
+
+            if let Some(extends) = &mut container.extends {
+                unalias_name(extends);
+            }
+
+            // Bindings to another container name it before the entity set: `T.Container/People`.
+            let bindings = container
+                .entity_sets
+                .iter_mut()
+                .flat_map(|entity_set| entity_set.navigation_property_bindings.iter_mut())
+                .chain(
+                    container
+                        .singletons
+                        .iter_mut()
+                        .flat_map(|singleton| singleton.navigation_property_bindings.iter_mut()),
+                );
+
+            for binding in bindings {
+                unalias_name(&mut binding.target);
+            }
+
+            for annotation in schema.annotations_mut() {
+                unalias_name(&mut annotation.term);
+
+                for nested in &mut annotation.annotations {
+                    unalias_name(&mut nested.term);
+                }
+            }
+
+            for annotations in &mut schema.annotations {
+                unalias_name(&mut annotations.target);
+            }
+        }
+    }
+
+    /// Give each container the children of any container it extends, following the chain of
+    /// extensions. Then, drop the containers of referenced documents, which the service doesn't
+    /// expose.
+    pub fn resolve_container_extensions(&mut self) {
+        let containers: BTreeMap<String, EntityContainer> = self
+            .data_services
+            .schema
+            .iter()
+            .map(|schema| {
+                let name = format!("{}.{}", schema.namespace, schema.entity_container.name);
+                (name, schema.entity_container.clone())
+            })
+            .collect();
+
+        for schema in &mut self.data_services.schema {
+            let mut extends = schema.entity_container.extends.clone();
+            let mut visited = BTreeSet::new();
+
+            // A cycle of extensions adds nothing after the first lap, so we stop there.
+            while let Some(name) = extends.filter(|name| visited.insert(name.clone())) {
+                match containers.get(&name) {
+                    Some(base) => {
+                        schema.entity_container.inherit(base);
+                        extends = base.extends.clone();
+                    }
+
+                    None => break,
+                }
+            }
+
+            if schema.is_referenced {
+                schema.entity_container = EntityContainer::default();
+            }
+        }
+    }
+
+    /// Replace every reference to a type definition with the primitive type that it names. After
+    /// this, we never need to think about type definitions again.
+    pub fn resolve_type_definitions(&mut self) {
+        let mut type_definitions = BTreeMap::new();
+
+        for schema in &self.data_services.schema {
+            for type_definition in &schema.type_definitions {
+                let name = format!("{}.{}", schema.namespace, type_definition.name);
+                type_definitions.insert(name, type_definition.clone());
+            }
+        }
+
+        if type_definitions.is_empty() {
+            return;
+        }
+
+        for schema in &mut self.data_services.schema {
+            for type_data in schema.types_mut() {
+                type_data.resolve(&type_definitions);
+            }
+        }
+    }
+
     pub fn schema(&self, name: &str) -> Option<&Schema> {
         self.data_services.schema(name)
     }
 
+    /// Every annotation that applies to a model element: those written inline, followed by any
+    /// out-of-line annotations that target it. Qualified annotations are meant for particular
+    /// consumers, so we ignore them.
+    pub fn applicable_annotations<'a>(
+        &'a self,
+        target: &str,
+        inline: &'a [Annotation],
+    ) -> Vec<&'a Annotation> {
+        inline
+            .iter()
+            .chain(self.annotations(target))
+            .filter(|annotation| annotation.qualifier.is_none())
+            .collect()
+    }
+
+    /// The out-of-line annotations that target the given model element, from every schema.
+    pub fn annotations(&self, target: &str) -> impl Iterator<Item = &Annotation> {
+        let target = target.to_string();
+
+        self.data_services
+            .schema
+            .iter()
+            .flat_map(|schema| schema.annotations.iter())
+            .filter(|annotations| annotations.qualifier.is_none())
+            .filter(move |annotations| annotations.applies_to(&target))
+            .flat_map(|annotations| annotations.annotations.iter())
+    }
+
     pub fn complex_type(&self, qualified_type: &QualifiedType) -> Option<ComplexType> {
         self.schema(&qualified_type.schema)?
             .complex_type(&qualified_type.name)
@@ -45,6 +258,166 @@ impl EDMX {
         self.schema(&qualified_type.schema)?
             .entity_type(&qualified_type.name)
     }
+
+    /// An entity type followed by each of its base types in turn. We stop if we find a cycle or a
+    /// missing base type: validation reports both.
+    pub fn ancestry(&self, entity_type: &QualifiedType) -> Vec<QualifiedType> {
+        let mut ancestry = Vec::new();
+        let mut current = Some(entity_type.clone());
+
+        while let Some(qualified_type) = current.filter(|x| !ancestry.contains(x)) {
+            current = self
+                .entity_type(&qualified_type)
+                .and_then(|entity_type| entity_type.base_type);
+
+            ancestry.push(qualified_type);
+        }
+
+        ancestry
+    }
+
+    /// The base types of an entity type or complex type, nearest first, given the type's own base
+    /// type. As with `ancestry`, we stop at a cycle or a missing base type.
+    pub fn base_types(&self, base_type: Option<&QualifiedType>) -> Vec<QualifiedType> {
+        let mut base_types: Vec<QualifiedType> = Vec::new();
+        let mut current = base_type.cloned();
+
+        while let Some(qualified_type) = current.filter(|x| !base_types.contains(x)) {
+            current = match (
+                self.entity_type(&qualified_type),
+                self.complex_type(&qualified_type),
+            ) {
+                (Some(entity_type), _) => entity_type.base_type,
+                (None, Some(complex_type)) => complex_type.base_type,
+                (None, None) => break,
+            };
+
+            base_types.push(qualified_type);
+        }
+
+        base_types
+    }
+
+    /// The properties that a type inherits from its base types, nearest first.
+    pub fn inherited_properties(&self, base_type: Option<&QualifiedType>) -> Vec<Property> {
+        self.base_types(base_type)
+            .iter()
+            .flat_map(|base_type| match self.entity_type(base_type) {
+                Some(entity_type) => entity_type.properties,
+                None => self
+                    .complex_type(base_type)
+                    .map(|complex_type| complex_type.properties)
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// The navigation properties that a type inherits from its base types, nearest first.
+    pub fn inherited_navigation_properties(
+        &self,
+        base_type: Option<&QualifiedType>,
+    ) -> Vec<NavigationProperty> {
+        self.base_types(base_type)
+            .iter()
+            .flat_map(|base_type| match self.entity_type(base_type) {
+                Some(entity_type) => entity_type.navigation_properties,
+                None => self
+                    .complex_type(base_type)
+                    .map(|complex_type| complex_type.navigation_properties)
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// An entity type or complex type and each of its base types, starting from the root of the
+    /// hierarchy, along with the properties that each declares. Out-of-line annotations target a
+    /// property through the type that declares it (`Trippin.Person/UserName`, even for an
+    /// employee), so this is how we find them. As with `ancestry`, we stop at a cycle or a missing
+    /// base type.
+    pub fn declaring_types(
+        &self,
+        structured_type: &QualifiedType,
+    ) -> Vec<(QualifiedType, Vec<Property>)> {
+        let mut declaring_types: Vec<(QualifiedType, Vec<Property>)> = Vec::new();
+        let mut current = Some(structured_type.clone());
+
+        while let Some(qualified_type) =
+            current.filter(|x| declaring_types.iter().all(|(seen, _)| seen != x))
+        {
+            let (properties, base_type) = match (
+                self.entity_type(&qualified_type),
+                self.complex_type(&qualified_type),
+            ) {
+                (Some(entity_type), _) => (entity_type.properties, entity_type.base_type),
+                (None, Some(complex_type)) => (complex_type.properties, complex_type.base_type),
+                (None, None) => break,
+            };
+
+            declaring_types.push((qualified_type, properties));
+            current = base_type;
+        }
+
+        declaring_types.reverse();
+        declaring_types
+    }
+
+    /// Every entity type that extends the given entity type, directly or otherwise.
+    pub fn derived_types(&self, entity_type: &QualifiedType) -> Vec<QualifiedType> {
+        let mut derived_types = Vec::new();
+
+        for schema in &self.data_services.schema {
+            for candidate in &schema.entity_types {
+                let qualified_type = QualifiedType {
+                    schema: schema.namespace.clone(),
+                    name: candidate.name.clone(),
+                };
+
+                if self.ancestry(&qualified_type)[1..].contains(entity_type) {
+                    derived_types.push(qualified_type);
+                }
+            }
+        }
+
+        derived_types
+    }
+
+    /// Find the overloads of the unbound function that a function import refers to by its
+    /// namespace-qualified name.
+    pub fn unbound_functions(&self, qualified_name: &str) -> Vec<Function> {
+        match qualified_name.rsplit_once('.') {
+            Some((namespace, name)) => self
+                .schema(namespace)
+                .map(|schema| schema.unbound_functions(name))
+                .unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Find the unbound action that an action import refers to by its namespace-qualified name.
+    pub fn unbound_action(&self, qualified_name: &str) -> Option<Action> {
+        let (namespace, name) = qualified_name.rsplit_once('.')?;
+        self.schema(namespace)?.unbound_action(name)
+    }
+
+    /// Find the entity set that a navigation property binding targets. The target is either the
+    /// name of an entity set in the binding's own container, or the qualified name of another
+    /// container followed by the name of one of its entity sets: `Trippin.Container/People`. By
+    /// now, containers have inherited the entity sets of the containers they extend, and the
+    /// containers of referenced documents are gone, so we also look for those in our own.
+    pub fn binding_target(&self, schema: &Schema, target: &str) -> Option<EntitySet> {
+        match target.split_once('/') {
+            Some((container, name)) if container.contains('.') => {
+                let (namespace, container) = container.rsplit_once('.')?;
+
+                self.schema(namespace)
+                    .filter(|other| other.entity_container.name == container)
+                    .and_then(|other| other.entity_set(name))
+                    .or_else(|| schema.entity_set(name))
+            }
+
+            _ => schema.entity_set(target),
+        }
+    }
 }
 
 /// The schema version. We require this to be 4.0 or 4.01, and otherwise, we can reject the
@@ -53,6 +426,13 @@ impl EDMX {
 #[derive(Clone, Debug)]
 pub struct Version(String);
 
+impl Version {
+    /// The version string exactly as the service declared it.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 impl<'de> Deserialize<'de> for Version {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let version_string = String::deserialize(deserializer)?;
@@ -78,6 +458,8 @@ pub struct DataServices {
 
 impl DataServices {
     pub fn schema(&self, name: &str) -> Option<&Schema> {
-        self.schema.iter().find(|target| target.namespace == name)
+        self.schema
+            .iter()
+            .find(|target| target.namespace == name || target.alias.as_deref() == Some(name))
     }
 }
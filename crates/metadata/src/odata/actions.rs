@@ -2,8 +2,8 @@
 
 use serde::Deserialize;
 
-/// Actions are OData's answer to the NDC notion of commands.
-/// @TODO: actions don't necessarily return values; do we need to use e.g. `true` for GraphQL?
+/// Actions are OData's answer to the NDC notion of commands. Actions don't necessarily return
+/// values, in which case we give their procedures the `OData.MutationResult` type instead.
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Action {
@@ -24,6 +24,10 @@ pub struct Action {
 
     #[serde(rename = "EntitySetPath")]
     pub entity_set_path: Option<String>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
 
 /// A declaration that the given action is available within the parent entity container.
@@ -37,4 +41,8 @@ pub struct ActionImport {
 
     #[serde(rename = "@EntitySet")]
     pub entity_set: Option<String>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
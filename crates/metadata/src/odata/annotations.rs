@@ -0,0 +1,337 @@
+//! Vocabulary annotations attach extra information to model elements: descriptions, units,
+//! capabilities, and so on. They can be written inline, as children of the element they annotate,
+//! or out of line, in an `Annotations` block that names its target by path.
+
+use serde::Deserialize;
+
+/// The application of a vocabulary term to a model element. We only understand terms whose values
+/// are constants or paths, or records and collections of those: everything else is ignored.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Annotation {
+    /// The namespace-qualified name of the term, such as `Org.OData.Core.V1.Description`.
+    #[serde(rename = "@Term")]
+    pub term: String,
+
+    #[serde(rename = "@Qualifier")]
+    pub qualifier: Option<String>,
+
+    #[serde(rename = "@String")]
+    pub string: Option<String>,
+
+    #[serde(rename = "@Path")]
+    pub path: Option<String>,
+
+    #[serde(rename = "@Bool")]
+    pub bool: Option<String>,
+
+    /// One or more members of an enum type, separated by spaces: `Core.Permission/Read`.
+    #[serde(rename = "@EnumMember")]
+    pub enum_member: Option<String>,
+
+    #[serde(rename = "@Int")]
+    pub int: Option<String>,
+
+    #[serde(rename = "@Decimal")]
+    pub decimal: Option<String>,
+
+    #[serde(rename = "@Float")]
+    pub float: Option<String>,
+
+    #[serde(rename = "@Date")]
+    pub date: Option<String>,
+
+    #[serde(rename = "@DateTimeOffset")]
+    pub date_time_offset: Option<String>,
+
+    #[serde(rename = "@TimeOfDay")]
+    pub time_of_day: Option<String>,
+
+    #[serde(rename = "@Duration")]
+    pub duration: Option<String>,
+
+    /// Long strings are often written as a child element rather than an attribute.
+    #[serde(rename = "String")]
+    pub string_element: Option<String>,
+
+    #[serde(rename = "Path")]
+    pub path_element: Option<String>,
+
+    #[serde(rename = "EnumMember")]
+    pub enum_member_element: Option<String>,
+
+    /// Structured values, such as `Capabilities.FilterRestrictions`, are records of named values.
+    #[serde(rename = "Record")]
+    pub record: Option<Record>,
+
+    #[serde(rename = "Collection")]
+    pub collection: Option<CollectionValue>,
+
+    /// Annotations can themselves be annotated: `Validation.Minimum` is made exclusive with a
+    /// nested `Validation.Exclusive`.
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<Annotation>,
+}
+
+/// Find the annotation of a vocabulary's term, such as `Description` in `Org.OData.Core.V1`, among
+/// those that apply to a model element.
+pub fn find_term<'a>(
+    annotations: &[&'a Annotation],
+    vocabulary: &str,
+    name: &str,
+) -> Option<&'a Annotation> {
+    let term = format!("{vocabulary}.{name}");
+
+    annotations
+        .iter()
+        .find(|annotation| annotation.term == term)
+        .copied()
+}
+
+impl Annotation {
+    /// The annotation's value, if it is a string.
+    pub fn string(&self) -> Option<&str> {
+        self.string
+            .as_ref()
+            .or(self.string_element.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Does a tag term, such as `Validation.Exclusive`, apply? Tags apply unless they're
+    /// explicitly false.
+    pub fn is_set(&self) -> bool {
+        self.bool.as_deref().map(str::trim) != Some("false")
+    }
+
+    /// The annotation's value, if it is a string, a number, or a temporal value, written as it
+    /// appears in the metadata.
+    pub fn constant(&self) -> Option<&str> {
+        [
+            &self.string,
+            &self.string_element,
+            &self.int,
+            &self.decimal,
+            &self.float,
+            &self.date,
+            &self.date_time_offset,
+            &self.time_of_day,
+            &self.duration,
+        ]
+        .into_iter()
+        .find_map(Option::as_deref)
+    }
+
+    /// The names of the enum members that make up the annotation's value, without their types:
+    /// `Core.Permission/Read Core.Permission/Write` gives `Read` and `Write`. JSON CSDL separates
+    /// members with commas instead.
+    pub fn enum_members(&self) -> Vec<&str> {
+        self.enum_member
+            .as_ref()
+            .or(self.enum_member_element.as_ref())
+            .into_iter()
+            .flat_map(|members| members.split([' ', '\t', '\n', ',']))
+            .filter(|member| !member.is_empty())
+            .map(|member| member.rsplit('/').next().unwrap_or(member))
+            .collect()
+    }
+
+    /// The annotation's value, if it is a path to another property.
+    pub fn path(&self) -> Option<&str> {
+        self.path
+            .as_ref()
+            .or(self.path_element.as_ref())
+            .map(String::as_str)
+    }
+}
+
+/// A structured annotation value, made up of named property values.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Record {
+    #[serde(default)]
+    #[serde(rename = "PropertyValue")]
+    pub property_values: Vec<PropertyValue>,
+}
+
+impl Record {
+    /// Look up one of the record's values by name.
+    pub fn property(&self, name: &str) -> Option<&PropertyValue> {
+        self.property_values
+            .iter()
+            .find(|property_value| property_value.property == name)
+    }
+}
+
+/// One named value within a record.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PropertyValue {
+    #[serde(rename = "@Property")]
+    pub property: String,
+
+    #[serde(rename = "@String")]
+    pub string: Option<String>,
+
+    #[serde(rename = "@Int")]
+    pub int: Option<String>,
+
+    #[serde(rename = "@Decimal")]
+    pub decimal: Option<String>,
+
+    #[serde(rename = "@Float")]
+    pub float: Option<String>,
+
+    #[serde(rename = "@Date")]
+    pub date: Option<String>,
+
+    #[serde(rename = "@DateTimeOffset")]
+    pub date_time_offset: Option<String>,
+
+    #[serde(rename = "String")]
+    pub string_element: Option<String>,
+
+    #[serde(rename = "@Bool")]
+    pub bool: Option<String>,
+
+    #[serde(rename = "Bool")]
+    pub bool_element: Option<String>,
+
+    #[serde(rename = "@EnumMember")]
+    pub enum_member: Option<String>,
+
+    #[serde(rename = "EnumMember")]
+    pub enum_member_element: Option<String>,
+
+    #[serde(rename = "@NavigationPropertyPath")]
+    pub navigation_property_path: Option<String>,
+
+    #[serde(rename = "NavigationPropertyPath")]
+    pub navigation_property_path_element: Option<String>,
+
+    #[serde(rename = "Record")]
+    pub record: Option<Record>,
+
+    #[serde(rename = "Collection")]
+    pub collection: Option<CollectionValue>,
+}
+
+impl PropertyValue {
+    /// The value, if it is a boolean.
+    pub fn bool(&self) -> Option<bool> {
+        match self.bool.as_ref().or(self.bool_element.as_ref())?.trim() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// The value, if it is a string, a number, or a temporal value, written as it appears in the
+    /// metadata.
+    pub fn constant(&self) -> Option<&str> {
+        [
+            &self.string,
+            &self.string_element,
+            &self.int,
+            &self.decimal,
+            &self.float,
+            &self.date,
+            &self.date_time_offset,
+        ]
+        .into_iter()
+        .find_map(Option::as_deref)
+    }
+
+    /// The value, if it is a member of an enum type, without the type's name: `None`, rather than
+    /// `Capabilities.NavigationType/None`. JSON documents write these as plain strings.
+    pub fn enum_member(&self) -> Option<&str> {
+        let member = [&self.enum_member, &self.enum_member_element, &self.string]
+            .into_iter()
+            .find_map(Option::as_deref)?;
+
+        Some(member.rsplit('/').next().unwrap_or(member).trim())
+    }
+
+    /// The value, if it is a path to a navigation property.
+    pub fn navigation_property_path(&self) -> Option<&str> {
+        self.navigation_property_path
+            .as_ref()
+            .or(self.navigation_property_path_element.as_ref())
+            .map(String::as_str)
+    }
+
+    /// The value, if it is a collection of records.
+    pub fn records(&self) -> &[Record] {
+        match &self.collection {
+            Some(collection) => &collection.records,
+            None => &[],
+        }
+    }
+
+    /// The value, if it is a collection of paths to properties or navigation properties.
+    pub fn paths(&self) -> Vec<String> {
+        match &self.collection {
+            Some(collection) => collection
+                .property_paths
+                .iter()
+                .chain(collection.navigation_property_paths.iter())
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// A collection of values within an annotation. We only understand collections of paths and of
+/// records.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CollectionValue {
+    #[serde(default)]
+    #[serde(rename = "Record")]
+    pub records: Vec<Record>,
+
+    #[serde(default)]
+    #[serde(rename = "PropertyPath")]
+    pub property_paths: Vec<String>,
+
+    #[serde(default)]
+    #[serde(rename = "NavigationPropertyPath")]
+    pub navigation_property_paths: Vec<String>,
+}
+
+/// A block of annotations that all apply to the same target, such as `Trippin.Person/FirstName`
+/// or `Trippin.Container/People`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Annotations {
+    #[serde(rename = "@Target")]
+    pub target: String,
+
+    #[serde(rename = "@Qualifier")]
+    pub qualifier: Option<String>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<Annotation>,
+}
+
+impl Annotations {
+    /// Does this block apply to the given target? Operation targets can name a specific overload
+    /// by its parameter types, as in `Trippin.GetNearestAirport(Edm.Double,Edm.Double)`, but we
+    /// don't distinguish between overloads.
+    pub fn applies_to(&self, target: &str) -> bool {
+        let (head, rest) = match self.target.split_once('/') {
+            Some((head, rest)) => (head, Some(rest)),
+            None => (self.target.as_str(), None),
+        };
+
+        let head = match head.split_once('(') {
+            Some((name, _)) => name,
+            None => head,
+        };
+
+        match (target.split_once('/'), rest) {
+            (Some((target_head, target_rest)), Some(rest)) => {
+                head == target_head && rest == target_rest
+            }
+            (None, None) => head == target,
+            _ => false,
+        }
+    }
+}
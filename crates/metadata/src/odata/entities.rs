@@ -23,6 +23,10 @@ pub struct EntitySet {
     #[serde(default)]
     #[serde(rename = "NavigationPropertyBinding")]
     pub navigation_property_bindings: Vec<NavigationPropertyBinding>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
 
 /// A declaration that a indicates that a particular navigation property should be resolved to a
@@ -57,6 +61,18 @@ pub struct EntityType {
     #[serde(rename = "@BaseType")]
     pub base_type: Option<super::QualifiedType>,
 
+    /// Abstract entity types can't be instantiated, only extended, and needn't have a key.
+    #[serde(default)]
+    #[serde(rename = "@Abstract")]
+    #[serde(deserialize_with = "super::helpers::str_to_bool")]
+    pub is_abstract: bool,
+
+    /// Open entity types may have properties that aren't declared in the metadata.
+    #[serde(default)]
+    #[serde(rename = "@OpenType")]
+    #[serde(deserialize_with = "super::helpers::str_to_bool")]
+    pub is_open: bool,
+
     #[serde(default)]
     #[serde(rename = "Property")]
     pub properties: Vec<super::Property>,
@@ -64,120 +80,149 @@ pub struct EntityType {
     #[serde(default)]
     #[serde(rename = "NavigationProperty")]
     pub navigation_properties: Vec<super::NavigationProperty>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
 
 impl EntityType {
     /// Get all the fields from the type and the chain of base types.
     pub fn fields(&self, metadata: &super::EDMX) -> Vec<super::Property> {
-        let mut collection = Vec::new();
-        collection.append(&mut self.properties.clone());
-
-        if let Some(target) = self
-            .base_type
-            .as_ref()
-            .and_then(|x| metadata.entity_type(x))
-        {
-            collection.append(&mut target.fields(metadata))
-        }
-
-        if let Some(target) = self
-            .base_type
-            .as_ref()
-            .and_then(|x| metadata.complex_type(x))
-        {
-            collection.append(&mut target.fields(metadata))
-        }
-
-        collection
+        let mut fields = self.properties.clone();
+        fields.append(&mut metadata.inherited_properties(self.base_type.as_ref()));
+        fields
     }
 
     /// Get all the navigation properties from the type and the chain of base types.
     pub fn navigation_properties(&self, metadata: &super::EDMX) -> Vec<super::NavigationProperty> {
-        let mut collection = Vec::new();
-        collection.append(&mut self.navigation_properties.clone());
-
-        if let Some(target) = self
-            .base_type
-            .as_ref()
-            .and_then(|x| metadata.entity_type(x))
-        {
-            collection.append(&mut target.navigation_properties(metadata))
-        }
+        let mut navigation_properties = self.navigation_properties.clone();
+        navigation_properties
+            .append(&mut metadata.inherited_navigation_properties(self.base_type.as_ref()));
+        navigation_properties
+    }
 
-        if let Some(target) = self
-            .base_type
-            .as_ref()
-            .and_then(|x| metadata.complex_type(x))
-        {
-            collection.append(&mut target.navigation_properties(metadata))
+    /// Find a navigation property by its binding path. The path may begin with a cast to a derived
+    /// type (as in `Trippin.Employee/Peers`), in which case we look for the property on that type.
+    pub fn navigation_property(
+        &self,
+        metadata: &super::EDMX,
+        path: &str,
+    ) -> Option<super::NavigationProperty> {
+        match path.rsplit_once('/') {
+            Some((cast, name)) => {
+                let (schema, type_name) = cast.rsplit_once('.')?;
+                let derived = metadata.entity_type(&super::QualifiedType {
+                    schema: schema.to_string(),
+                    name: type_name.to_string(),
+                })?;
+
+                derived.navigation_property(metadata, name)
+            }
+
+            None => self
+                .navigation_properties(metadata)
+                .into_iter()
+                .find(|navigation_property| navigation_property.name == path),
         }
-
-        collection
     }
 
-    /// Get the name of the key for this entity type, potentially checking through the base type
-    /// ancestry to find it.
-    pub fn key_name(&self, metadata: &super::EDMX) -> String {
+    /// Get the properties that make up the key for this entity type, potentially checking through
+    /// the base type ancestry to find them. Abstract entity types needn't have a key at all.
+    pub fn key(&self, metadata: &super::EDMX) -> Option<Vec<PropertyRef>> {
         match &self.key {
-            Some(key) => key.property_ref.name.clone(),
-            None => match &self.base_type {
-                Some(base_type) => match metadata.entity_type(base_type) {
-                    Some(entity_type) => entity_type.key_name(metadata).to_string(),
-                    None => panic!("Can't find base type for {}", self.name),
-                },
-                None => panic!("Key type {} has neither a key nor a base type", self.name),
-            },
+            Some(key) => Some(key.property_refs.clone()),
+            None => metadata
+                .base_types(self.base_type.as_ref())
+                .iter()
+                .find_map(|base_type| metadata.entity_type(base_type)?.key)
+                .map(|key| key.property_refs),
         }
     }
 
-    /// Get the type of the key in this entity type. If the entity type has a key, we look up the
-    /// type of that key in all the fields of the current entity and its ancestors. If it doesn't,
-    /// we have to look up the base type ancestors to find a key.
-    pub fn key_type(&self, metadata: &super::EDMX) -> super::QualifiedType {
-        match &self.key {
-            Some(key) => self
+    /// Get the type of each part of the key in this entity type. We look up the type of each part
+    /// in all the fields of the current entity and its ancestors, following the part's path into
+    /// complex properties if need be. Parts that we can't find are left out: validation reports
+    /// them against the collection's key.
+    pub fn key_types(&self, metadata: &super::EDMX) -> Vec<(PropertyRef, super::QualifiedType)> {
+        self.key(metadata)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|property_ref| {
+                let key_type = self.property_type(metadata, &property_ref.name)?;
+                Some((property_ref, key_type))
+            })
+            .collect()
+    }
+
+    /// Find the type of a property by its path: `Address/City` is the `City` property of the
+    /// complex `Address` property.
+    pub fn property_type(
+        &self,
+        metadata: &super::EDMX,
+        path: &str,
+    ) -> Option<super::QualifiedType> {
+        let mut segments = path.split('/');
+        let first = segments.next()?;
+
+        let mut property = self
+            .fields(metadata)
+            .into_iter()
+            .find(|property| property.name == first)?;
+
+        for segment in segments {
+            property = metadata
+                .complex_type(property.underlying_type())?
                 .fields(metadata)
-                .iter()
-                .find(|property| property.name == key.property_ref.name)
-                .unwrap()
-                .underlying_type()
-                .clone(),
-            None => match &self.base_type {
-                Some(base_type) => match metadata.entity_type(base_type) {
-                    Some(entity) => entity.key_type(metadata),
-                    None => panic!("Base type {} doesn't exist", base_type),
-                },
-
-                None => panic!("Key type {} has neither a key nor a base type", self.name),
-            },
+                .into_iter()
+                .find(|property| property.name == segment)?;
         }
+
+        Some(property.underlying_type().clone())
     }
 }
 
 /// The "key" of an entity type. This is the unique identifier of any given resource within the
-/// entity set, and we can think of it as a primary key.
+/// entity set, and we can think of it as a primary key. Keys may be made up of several properties.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Key {
     #[serde(rename = "PropertyRef")]
-    pub property_ref: PropertyRef,
+    pub property_refs: Vec<PropertyRef>,
 }
 
-/// The property on the entity type that we're using as our key.
+/// A property on the entity type that makes up part of our key. This may be a path to a property
+/// of a complex property, in which case it must have an alias: key predicates refer to the part by
+/// its alias rather than its path.
 #[derive(Clone, Debug, Deserialize)]
 pub struct PropertyRef {
     #[serde(rename = "@Name")]
     pub name: String,
+
+    #[serde(rename = "@Alias")]
+    pub alias: Option<String>,
+}
+
+impl PropertyRef {
+    /// The name by which key predicates refer to this part of the key.
+    pub fn key_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
 }
 
 /// An entity container describes the available API given the defined entity types, functions, and
 /// actions. Specifically, in the parlance of NDCs, this means that it describes the collections
 /// (entity sets), functions (functions and singletons), and procedures available within this
 /// particular schema.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct EntityContainer {
     #[serde(rename = "@Name")]
     pub name: String,
 
+    /// The namespace-qualified name of another container, all of whose children this container
+    /// also exposes.
+    #[serde(rename = "@Extends")]
+    pub extends: Option<String>,
+
     #[serde(default)]
     #[serde(rename = "EntitySet")]
     pub entity_sets: Vec<EntitySet>,
@@ -195,6 +240,26 @@ pub struct EntityContainer {
     pub action_imports: Vec<super::ActionImport>,
 }
 
+impl EntityContainer {
+    /// Take on the children of an extended container. Our own children win any name conflicts.
+    pub fn inherit(&mut self, base: &EntityContainer) {
+        inherit(&mut self.entity_sets, &base.entity_sets, |x| &x.name);
+        inherit(&mut self.singletons, &base.singletons, |x| &x.name);
+        inherit(&mut self.function_imports, &base.function_imports, |x| {
+            &x.name
+        });
+        inherit(&mut self.action_imports, &base.action_imports, |x| &x.name);
+    }
+}
+
+fn inherit<T: Clone>(own: &mut Vec<T>, base: &[T], name: impl Fn(&T) -> &String) {
+    for child in base {
+        if !own.iter().any(|existing| name(existing) == name(child)) {
+            own.push(child.clone());
+        }
+    }
+}
+
 /// Singletons are conceptually equivalent to nullary functions within the NDC vocabulary: they are
 /// defined at the entity container level, and return a singular row.
 #[derive(Clone, Debug, Deserialize)]
@@ -208,4 +273,8 @@ pub struct Singleton {
     #[serde(default)]
     #[serde(rename = "NavigationPropertyBinding")]
     pub navigation_property_bindings: Vec<NavigationPropertyBinding>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
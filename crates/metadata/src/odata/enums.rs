@@ -8,16 +8,40 @@ pub struct EnumType {
     #[serde(rename = "@Name")]
     pub name: String,
 
+    /// The integer type of the members' values. If this is missing, it's `Edm.Int32`.
+    #[serde(rename = "@UnderlyingType")]
+    pub underlying_type: Option<super::QualifiedType>,
+
+    /// Flag enums allow a value to be any combination of members, written as `'Red,Blue'`.
+    #[serde(default)]
+    #[serde(rename = "@IsFlags")]
+    #[serde(deserialize_with = "super::helpers::str_to_bool")]
+    pub is_flags: bool,
+
+    #[serde(default)]
     #[serde(rename = "Member")]
     pub members: Vec<Member>,
 }
 
-/// A member of an enum type: a name, along with a corresponding integer value.
+impl EnumType {
+    /// The integer type of the members' values.
+    pub fn underlying_type(&self) -> super::QualifiedType {
+        self.underlying_type
+            .clone()
+            .unwrap_or_else(|| super::QualifiedType {
+                schema: "Edm".to_string(),
+                name: "Int32".to_string(),
+            })
+    }
+}
+
+/// A member of an enum type: a name, along with a corresponding integer value. If the value is
+/// missing, the members are numbered from zero in the order they're declared.
 #[derive(Clone, Debug, Deserialize)]
 pub struct Member {
     #[serde(rename = "@Name")]
     pub name: String,
 
     #[serde(rename = "@Value")]
-    pub value: u32,
+    pub value: Option<i64>,
 }
@@ -25,6 +25,29 @@ pub struct Function {
     #[serde(rename = "@IsBound")]
     #[serde(deserialize_with = "super::helpers::str_to_bool")]
     pub is_bound: bool,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
+}
+
+impl Function {
+    /// If this function returns a collection of entities, get their entity type. The results of
+    /// such functions can be queried further with `$filter`, `$expand` and friends, exactly like
+    /// an entity set.
+    pub fn entity_collection_type(&self, metadata: &super::EDMX) -> Option<super::QualifiedType> {
+        match &self.return_type.inner {
+            super::Type::Collection { elements } => {
+                let element_type = elements.underlying_type();
+
+                metadata
+                    .entity_type(element_type)
+                    .map(|_| element_type.clone())
+            }
+
+            super::Type::Qualified { .. } => None,
+        }
+    }
 }
 
 /// A parameter for a function or action.
@@ -35,6 +58,10 @@ pub struct Parameter {
 
     #[serde(flatten)]
     pub r#type: super::TypeData,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
 
 /// A declaration that the given function is available within the parent entity container.
@@ -48,4 +75,8 @@ pub struct FunctionImport {
 
     #[serde(rename = "@EntitySet")]
     pub entity_set: Option<String>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
@@ -0,0 +1,596 @@
+//! OData 4.01 services can describe themselves in JSON (`$metadata?$format=json`) as well as in
+//! XML, and some only speak JSON. Rather than maintain a second model, we rewrite a JSON CSDL
+//! document into the shape that the XML deserializer sees (`@Name` attributes, `Property`
+//! children, and so on), then deserialize that into the same `EDMX` model.
+//!
+//! The two forms differ in their defaults: a JSON member without `$Type` is an `Edm.String`, and
+//! one without `$Nullable` isn't nullable, so we spell these out as we go.
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// The terms whose values are members of an enum type. JSON CSDL writes enum members as strings,
+/// so the term is the only way to tell them apart from string values.
+const ENUM_TERMS: [&str; 2] = [
+    "Org.OData.Core.V1.Permissions",
+    "Org.OData.Capabilities.V1.IsolationSupported",
+];
+
+/// Parse a JSON CSDL document.
+pub fn from_str(document: &str) -> serde_json::Result<super::EDMX> {
+    let document: Value = serde_json::from_str(document)?;
+    let mut edmx = edmx(&document);
+
+    let aliases = aliases(&edmx);
+    enum_members(&mut edmx, &aliases);
+
+    serde_json::from_value(edmx)
+}
+
+fn edmx(document: &Value) -> Value {
+    let mut edmx = Map::new();
+
+    if let Some(version) = document.get("$Version") {
+        edmx.insert("@Version".into(), version.clone());
+    }
+
+    let references: Vec<Value> = members(document, "$Reference")
+        .map(|(uri, reference)| {
+            let includes: Vec<Value> = array(reference, "$Include")
+                .map(|include| {
+                    let mut element = Map::new();
+                    copy(include, "$Namespace", &mut element, "@Namespace");
+                    copy(include, "$Alias", &mut element, "@Alias");
+                    Value::Object(element)
+                })
+                .collect();
+
+            let mut element = Map::new();
+            element.insert("@Uri".into(), Value::from(uri.as_str()));
+            element.insert("Include".into(), Value::from(includes));
+            Value::Object(element)
+        })
+        .collect();
+
+    edmx.insert("Reference".into(), Value::from(references));
+
+    // Every member that isn't a `$` keyword or an annotation is a schema, named by its namespace.
+    let schemas: Vec<Value> = elements(document)
+        .map(|(namespace, contents)| schema(namespace, contents))
+        .collect();
+
+    let mut data_services = Map::new();
+    data_services.insert("Schema".into(), Value::from(schemas));
+    edmx.insert("DataServices".into(), Value::Object(data_services));
+
+    Value::Object(edmx)
+}
+
+fn schema(namespace: &str, contents: &Value) -> Value {
+    let mut schema = Map::new();
+    schema.insert("@Namespace".into(), Value::from(namespace));
+    copy(contents, "$Alias", &mut schema, "@Alias");
+
+    for (name, element) in elements(contents) {
+        // Functions and actions are arrays of overloads: everything else is a single object.
+        let overloads = match element {
+            Value::Array(overloads) => overloads.iter().collect(),
+            _ => Vec::from([element]),
+        };
+
+        for element in overloads {
+            match element.get("$Kind").and_then(Value::as_str) {
+                Some(kind @ ("EntityType" | "ComplexType")) => {
+                    push(&mut schema, kind, structured_type(name, element))
+                }
+                Some("EnumType") => push(&mut schema, "EnumType", enum_type(name, element)),
+                Some("TypeDefinition") => push(
+                    &mut schema,
+                    "TypeDefinition",
+                    type_definition(name, element),
+                ),
+                Some(kind @ ("Function" | "Action")) => {
+                    push(&mut schema, kind, operation(name, element))
+                }
+                Some("EntityContainer") => {
+                    let container = entity_container(name, element);
+                    schema.insert("EntityContainer".into(), container);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let annotations: Vec<Value> = members(contents, "$Annotations")
+        .map(|(target, annotations)| {
+            let mut element = Map::new();
+            element.insert("@Target".into(), Value::from(target.as_str()));
+            element.insert("Annotation".into(), self::annotations(annotations));
+            Value::Object(element)
+        })
+        .collect();
+
+    schema.insert("Annotations".into(), Value::from(annotations));
+    Value::Object(schema)
+}
+
+/// An entity type or complex type. Both list their properties and navigation properties as
+/// members, told apart by `$Kind`.
+fn structured_type(name: &str, contents: &Value) -> Value {
+    let mut element = named(name, contents);
+    copy(contents, "$BaseType", &mut element, "@BaseType");
+    flag(contents, "$Abstract", &mut element, "@Abstract");
+    flag(contents, "$OpenType", &mut element, "@OpenType");
+
+    // Each part of the key is either a property path, or an object mapping an alias to a path.
+    if let Some(Value::Array(parts)) = contents.get("$Key") {
+        let property_refs: Vec<Value> = parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(path) => Some((path.as_str(), None)),
+                Value::Object(aliased) => aliased
+                    .iter()
+                    .next()
+                    .and_then(|(alias, path)| Some((path.as_str()?, Some(alias.as_str())))),
+                _ => None,
+            })
+            .map(|(path, alias)| {
+                let mut property_ref = Map::new();
+                property_ref.insert("@Name".into(), Value::from(path));
+
+                if let Some(alias) = alias {
+                    property_ref.insert("@Alias".into(), Value::from(alias));
+                }
+
+                Value::Object(property_ref)
+            })
+            .collect();
+
+        let mut key = Map::new();
+        key.insert("PropertyRef".into(), Value::from(property_refs));
+        element.insert("Key".into(), Value::Object(key));
+    }
+
+    for (name, member) in elements(contents) {
+        match member.get("$Kind").and_then(Value::as_str) {
+            Some("NavigationProperty") => {
+                push(&mut element, "NavigationProperty", navigation(name, member))
+            }
+            _ => push(&mut element, "Property", property(name, member)),
+        }
+    }
+
+    Value::Object(element)
+}
+
+fn property(name: &str, contents: &Value) -> Value {
+    let mut element = named(name, contents);
+    type_data(contents, &mut element);
+    Value::Object(element)
+}
+
+fn navigation(name: &str, contents: &Value) -> Value {
+    let mut element = named(name, contents);
+    type_data(contents, &mut element);
+    flag(contents, "$ContainsTarget", &mut element, "@ContainsTarget");
+
+    // Nullability means nothing for a collection of entities, and the XML form omits it.
+    if contents.get("$Collection") == Some(&Value::Bool(true)) {
+        element.remove("@Nullable");
+    }
+
+    let constraints: Vec<Value> = members(contents, "$ReferentialConstraint")
+        .filter(|(property, _)| !property.contains('@'))
+        .filter_map(|(property, referenced)| {
+            let mut constraint = Map::new();
+            constraint.insert("@Property".into(), Value::from(property.as_str()));
+            constraint.insert("@ReferencedProperty".into(), referenced.clone());
+            referenced.is_string().then_some(Value::Object(constraint))
+        })
+        .collect();
+
+    element.insert("ReferentialConstraint".into(), Value::from(constraints));
+    Value::Object(element)
+}
+
+fn enum_type(name: &str, contents: &Value) -> Value {
+    let mut element = named(name, contents);
+    copy(contents, "$UnderlyingType", &mut element, "@UnderlyingType");
+    flag(contents, "$IsFlags", &mut element, "@IsFlags");
+
+    let members: Vec<Value> = elements(contents)
+        .map(|(name, value)| {
+            let mut member = Map::new();
+            member.insert("@Name".into(), Value::from(name));
+            member.insert("@Value".into(), value.clone());
+            Value::Object(member)
+        })
+        .collect();
+
+    element.insert("Member".into(), Value::from(members));
+    Value::Object(element)
+}
+
+fn type_definition(name: &str, contents: &Value) -> Value {
+    let mut element = named(name, contents);
+    copy(contents, "$UnderlyingType", &mut element, "@UnderlyingType");
+    facets(contents, &mut element);
+    Value::Object(element)
+}
+
+/// One overload of a function or action.
+fn operation(name: &str, contents: &Value) -> Value {
+    let mut element = named(name, contents);
+    flag(contents, "$IsBound", &mut element, "@IsBound");
+    copy(contents, "$EntitySetPath", &mut element, "EntitySetPath");
+
+    for parameter in array(contents, "$Parameter") {
+        let name = parameter.get("$Name").and_then(Value::as_str).unwrap_or("");
+        push(&mut element, "Parameter", property(name, parameter));
+    }
+
+    if let Some(return_type) = contents.get("$ReturnType") {
+        let mut type_element = Map::new();
+        type_data(return_type, &mut type_element);
+        element.insert("ReturnType".into(), Value::Object(type_element));
+    }
+
+    Value::Object(element)
+}
+
+/// An entity container's children are told apart by their members: entity sets are collections,
+/// imports name their function or action, and anything else is a singleton.
+fn entity_container(name: &str, contents: &Value) -> Value {
+    let mut element = named(name, contents);
+    copy(contents, "$Extends", &mut element, "@Extends");
+
+    for (name, child) in elements(contents) {
+        let mut child_element = named(name, child);
+
+        if child.get("$Function").is_some() {
+            copy(child, "$Function", &mut child_element, "@Function");
+            copy(child, "$EntitySet", &mut child_element, "@EntitySet");
+            push(&mut element, "FunctionImport", Value::Object(child_element));
+        } else if child.get("$Action").is_some() {
+            copy(child, "$Action", &mut child_element, "@Action");
+            copy(child, "$EntitySet", &mut child_element, "@EntitySet");
+            push(&mut element, "ActionImport", Value::Object(child_element));
+        } else if child.get("$Collection") == Some(&Value::Bool(true)) {
+            copy(child, "$Type", &mut child_element, "@EntityType");
+            bindings(child, &mut child_element);
+            push(&mut element, "EntitySet", Value::Object(child_element));
+        } else {
+            // A singleton's nullability has the same default in both forms, so we pass it on
+            // only if it's given.
+            copy(child, "$Type", &mut child_element, "@Type");
+            flag(child, "$Nullable", &mut child_element, "@Nullable");
+            bindings(child, &mut child_element);
+            push(&mut element, "Singleton", Value::Object(child_element));
+        }
+    }
+
+    Value::Object(element)
+}
+
+fn bindings(contents: &Value, element: &mut Map<String, Value>) {
+    let bindings: Vec<Value> = members(contents, "$NavigationPropertyBinding")
+        .map(|(path, target)| {
+            let mut binding = Map::new();
+            binding.insert("@Path".into(), Value::from(path.as_str()));
+            binding.insert("@Target".into(), target.clone());
+            Value::Object(binding)
+        })
+        .collect();
+
+    element.insert("NavigationPropertyBinding".into(), Value::from(bindings));
+}
+
+/// The type of a property, parameter, or return type, along with its nullability and facets.
+fn type_data(contents: &Value, element: &mut Map<String, Value>) {
+    let underlying_type = contents
+        .get("$Type")
+        .and_then(Value::as_str)
+        .unwrap_or("Edm.String");
+
+    let r#type = match contents.get("$Collection") {
+        Some(Value::Bool(true)) => format!("Collection({underlying_type})"),
+        _ => underlying_type.to_string(),
+    };
+
+    let nullable = contents.get("$Nullable") == Some(&Value::Bool(true));
+
+    element.insert("@Type".into(), Value::from(r#type));
+    element.insert("@Nullable".into(), Value::from(nullable.to_string()));
+    facets(contents, element);
+}
+
+/// Facets are numbers, or symbolic values such as `max`, and we keep them as strings.
+fn facets(contents: &Value, element: &mut Map<String, Value>) {
+    for (member, attribute) in [
+        ("$MaxLength", "@MaxLength"),
+        ("$Precision", "@Precision"),
+        ("$Scale", "@Scale"),
+        ("$SRID", "@SRID"),
+        ("$DefaultValue", "@DefaultValue"),
+    ] {
+        if let Some(value) = contents.get(member).and_then(text) {
+            element.insert(attribute.into(), Value::from(value));
+        }
+    }
+}
+
+/// The annotations of a model element: its members whose names begin with `@`. An annotation of
+/// an annotation is named after both, as in `@Validation.Minimum@Validation.Exclusive`.
+fn annotations(contents: &Value) -> Value {
+    let mut annotations: Vec<(String, Map<String, Value>)> = Vec::new();
+    let mut nested: Vec<(String, Value)> = Vec::new();
+
+    let Some(object) = contents.as_object() else {
+        return Value::from(Vec::<Value>::new());
+    };
+
+    for (name, value) in object {
+        let Some(name) = name.strip_prefix('@') else {
+            continue;
+        };
+
+        match name.split_once('@') {
+            Some((parent, term)) => nested.push((parent.to_string(), annotation(term, value))),
+            None => {
+                if let Value::Object(annotation) = annotation(name, value) {
+                    annotations.push((name.to_string(), annotation));
+                }
+            }
+        }
+    }
+
+    for (parent, annotation) in nested {
+        if let Some((_, parent)) = annotations.iter_mut().find(|(name, _)| *name == parent) {
+            push(parent, "Annotation", annotation);
+        }
+    }
+
+    Value::from(
+        annotations
+            .into_iter()
+            .map(|(_, annotation)| Value::Object(annotation))
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// A single annotation, named `Term` or `Term#Qualifier`.
+fn annotation(name: &str, value: &Value) -> Value {
+    let mut element = Map::new();
+
+    match name.split_once('#') {
+        Some((term, qualifier)) => {
+            element.insert("@Term".into(), Value::from(term));
+            element.insert("@Qualifier".into(), Value::from(qualifier));
+        }
+        None => {
+            element.insert("@Term".into(), Value::from(name));
+        }
+    }
+
+    match value {
+        // This may yet turn out to be an enum member, once we know the term's namespace.
+        Value::String(string) => {
+            element.insert("@String".into(), Value::from(string.as_str()));
+        }
+
+        Value::Object(object) => match object.get("$Path") {
+            Some(path) => {
+                element.insert("@Path".into(), path.clone());
+            }
+            None => {
+                element.insert("Record".into(), record(object));
+            }
+        },
+
+        _ => constant(value, &mut element),
+    }
+
+    Value::Object(element)
+}
+
+/// Every alias in the rewritten document, mapped to the namespace it stands for.
+fn aliases(edmx: &Value) -> BTreeMap<String, String> {
+    let includes = array(edmx, "Reference").flat_map(|reference| array(reference, "Include"));
+    let schemas = edmx
+        .get("DataServices")
+        .into_iter()
+        .flat_map(|data_services| array(data_services, "Schema"));
+
+    includes
+        .chain(schemas)
+        .filter_map(|element| {
+            let alias = element.get("@Alias")?.as_str()?;
+            let namespace = element.get("@Namespace")?.as_str()?;
+            Some((alias.to_string(), namespace.to_string()))
+        })
+        .collect()
+}
+
+/// Turn the string values of annotations with enum-valued terms into enum members, wherever they
+/// appear in the rewritten document.
+fn enum_members(element: &mut Value, aliases: &BTreeMap<String, String>) {
+    match element {
+        Value::Array(children) => {
+            for child in children {
+                enum_members(child, aliases);
+            }
+        }
+
+        Value::Object(element) => {
+            let is_enum_term = element
+                .get("@Term")
+                .and_then(Value::as_str)
+                .and_then(|term| term.rsplit_once('.'))
+                .is_some_and(|(namespace, name)| {
+                    let namespace = aliases.get(namespace).map_or(namespace, String::as_str);
+                    ENUM_TERMS.contains(&format!("{namespace}.{name}").as_str())
+                });
+
+            if is_enum_term {
+                if let Some(members) = element.remove("@String") {
+                    element.insert("@EnumMember".into(), members);
+                }
+            }
+
+            for child in element.values_mut() {
+                enum_members(child, aliases);
+            }
+        }
+
+        _ => {}
+    }
+}
+
+fn record(object: &Map<String, Value>) -> Value {
+    let property_values: Vec<Value> = object
+        .iter()
+        .filter(|(name, _)| !name.starts_with(['@', '$']))
+        .map(|(name, value)| {
+            let mut property_value = Map::new();
+            property_value.insert("@Property".into(), Value::from(name.as_str()));
+
+            match value {
+                Value::String(string) => {
+                    property_value.insert("@String".into(), Value::from(string.as_str()));
+                }
+                Value::Object(object) => match object.get("$NavigationPropertyPath") {
+                    Some(path) => {
+                        property_value.insert("@NavigationPropertyPath".into(), path.clone());
+                    }
+                    None => {
+                        property_value.insert("Record".into(), record(object));
+                    }
+                },
+                _ => constant(value, &mut property_value),
+            }
+
+            Value::Object(property_value)
+        })
+        .collect();
+
+    let mut record = Map::new();
+    record.insert("PropertyValue".into(), Value::from(property_values));
+    Value::Object(record)
+}
+
+/// A boolean, a number, or a collection. Strings need more care, so we leave them to our callers.
+fn constant(value: &Value, element: &mut Map<String, Value>) {
+    match value {
+        Value::Bool(bool) => {
+            element.insert("@Bool".into(), Value::from(bool.to_string()));
+        }
+
+        Value::Number(number) if number.is_i64() || number.is_u64() => {
+            element.insert("@Int".into(), Value::from(number.to_string()));
+        }
+
+        Value::Number(number) => {
+            element.insert("@Decimal".into(), Value::from(number.to_string()));
+        }
+
+        Value::Array(items) => {
+            element.insert("Collection".into(), collection(items));
+        }
+
+        _ => {}
+    }
+}
+
+fn collection(items: &[Value]) -> Value {
+    let mut collection = Map::new();
+
+    for item in items {
+        let Value::Object(object) = item else {
+            continue;
+        };
+
+        if let Some(path) = object.get("$PropertyPath") {
+            push(&mut collection, "PropertyPath", path.clone());
+        } else if let Some(path) = object.get("$NavigationPropertyPath") {
+            push(&mut collection, "NavigationPropertyPath", path.clone());
+        } else {
+            push(&mut collection, "Record", record(object));
+        }
+    }
+
+    Value::Object(collection)
+}
+
+/// Start an element with its name and annotations.
+fn named(name: &str, contents: &Value) -> Map<String, Value> {
+    let mut element = Map::new();
+    element.insert("@Name".into(), Value::from(name));
+    element.insert("Annotation".into(), annotations(contents));
+    element
+}
+
+/// The members of an object that name model elements, rather than keywords or annotations.
+fn elements(contents: &Value) -> impl Iterator<Item = (&str, &Value)> {
+    contents
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| !name.starts_with(['$', '@']) && !name.contains('@'))
+        .map(|(name, value)| (name.as_str(), value))
+}
+
+/// The members of an object-valued keyword, such as `$NavigationPropertyBinding`.
+fn members<'a>(
+    contents: &'a Value,
+    keyword: &str,
+) -> impl Iterator<Item = (&'a String, &'a Value)> {
+    contents
+        .get(keyword)
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+}
+
+/// The items of an array-valued keyword, such as `$Parameter`.
+fn array<'a>(contents: &'a Value, keyword: &str) -> impl Iterator<Item = &'a Value> {
+    contents
+        .get(keyword)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+}
+
+/// Append a child to the list of children with the given tag.
+fn push(element: &mut Map<String, Value>, tag: &str, child: Value) {
+    match element
+        .entry(tag)
+        .or_insert_with(|| Value::Array(Vec::new()))
+    {
+        Value::Array(children) => children.push(child),
+        other => *other = Value::Array(Vec::from([child])),
+    }
+}
+
+/// Copy a keyword's value to an attribute, if it has one.
+fn copy(contents: &Value, keyword: &str, element: &mut Map<String, Value>, attribute: &str) {
+    if let Some(value) = contents.get(keyword).and_then(text) {
+        element.insert(attribute.into(), Value::from(value));
+    }
+}
+
+/// Copy a boolean keyword to an attribute, written the way XML writes it.
+fn flag(contents: &Value, keyword: &str, element: &mut Map<String, Value>, attribute: &str) {
+    if let Some(value) = contents.get(keyword).and_then(Value::as_bool) {
+        element.insert(attribute.into(), Value::from(value.to_string()));
+    }
+}
+
+/// A scalar as the text of an XML attribute.
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(string) => Some(string.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(bool) => Some(bool.to_string()),
+        _ => None,
+    }
+}
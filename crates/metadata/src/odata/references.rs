@@ -0,0 +1,39 @@
+//! Metadata documents can reference other metadata documents, and include some of the schemas
+//! that those documents define. This is how services share types, and how they bring vocabularies
+//! (such as `Org.OData.Core.V1`) into scope.
+
+use serde::Deserialize;
+
+/// A reference to another metadata document, which may be an absolute URL, or a path relative to
+/// the referencing document.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Reference {
+    #[serde(rename = "@Uri")]
+    pub uri: String,
+
+    #[serde(default)]
+    #[serde(rename = "Include")]
+    pub includes: Vec<Include>,
+}
+
+impl Reference {
+    /// Vocabularies are referenced by most services, but they only define annotation terms, which
+    /// we recognise by name. There's no need to fetch them. As well as the OASIS vocabularies,
+    /// services commonly use SAP's (such as `com.sap.vocabularies.Common.v1`).
+    pub fn is_vocabulary(&self) -> bool {
+        self.includes.iter().all(|include| {
+            include.namespace.starts_with("Org.OData.")
+                || include.namespace.starts_with("com.sap.vocabularies.")
+        })
+    }
+}
+
+/// A schema from a referenced document that is brought into scope, optionally under an alias.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Include {
+    #[serde(rename = "@Namespace")]
+    pub namespace: String,
+
+    #[serde(rename = "@Alias")]
+    pub alias: Option<String>,
+}
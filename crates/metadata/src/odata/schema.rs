@@ -9,6 +9,9 @@ pub struct Schema {
     #[serde(rename = "@Namespace")]
     pub namespace: String,
 
+    #[serde(rename = "@Alias")]
+    pub alias: Option<String>,
+
     #[serde(rename = "@xmlns")]
     pub xmlns: Option<String>,
 
@@ -24,6 +27,10 @@ pub struct Schema {
     #[serde(rename = "EnumType")]
     pub enum_types: Vec<super::EnumType>,
 
+    #[serde(default)]
+    #[serde(rename = "TypeDefinition")]
+    pub type_definitions: Vec<super::TypeDefinition>,
+
     #[serde(default)]
     #[serde(rename = "Function")]
     pub functions: Vec<super::Function>,
@@ -32,11 +39,130 @@ pub struct Schema {
     #[serde(rename = "Action")]
     pub actions: Vec<super::Action>,
 
+    /// Schemas that only define types (as referenced documents often do) have no container, in
+    /// which case they expose no collections, functions, or procedures.
+    #[serde(default)]
     #[serde(rename = "EntityContainer")]
     pub entity_container: super::EntityContainer,
+
+    /// Annotations that are written out of line, each block naming the element it annotates.
+    #[serde(default)]
+    #[serde(rename = "Annotations")]
+    pub annotations: Vec<super::Annotations>,
+
+    /// Was this schema included from a referenced document, rather than the service's own?
+    #[serde(skip)]
+    pub is_referenced: bool,
 }
 
 impl Schema {
+    /// Every type annotation in this schema that may name a type definition or an alias.
+    pub fn types_mut(&mut self) -> Vec<&mut super::TypeData> {
+        let entity_types = self.entity_types.iter_mut().flat_map(|entity_type| {
+            let properties = entity_type.properties.iter_mut().map(|x| &mut x.r#type);
+            let navigation = entity_type.navigation_properties.iter_mut();
+            properties.chain(navigation.map(|x| &mut x.r#type))
+        });
+
+        let complex_types = self.complex_types.iter_mut().flat_map(|complex_type| {
+            let properties = complex_type.properties.iter_mut().map(|x| &mut x.r#type);
+            let navigation = complex_type.navigation_properties.iter_mut();
+            properties.chain(navigation.map(|x| &mut x.r#type))
+        });
+
+        let functions = self.functions.iter_mut().flat_map(|function| {
+            let parameters = function.parameters.iter_mut().map(|x| &mut x.r#type);
+            parameters.chain(std::iter::once(&mut function.return_type))
+        });
+
+        let actions = self.actions.iter_mut().flat_map(|action| {
+            let parameters = action.parameters.iter_mut().map(|x| &mut x.r#type);
+            parameters.chain(action.return_type.as_mut())
+        });
+
+        let singletons = self.entity_container.singletons.iter_mut();
+
+        entity_types
+            .chain(complex_types)
+            .chain(functions)
+            .chain(actions)
+            .chain(singletons.map(|singleton| &mut singleton.r#type))
+            .collect()
+    }
+
+    /// Every annotation in this schema, whether it is written inline or out of line.
+    pub fn annotations_mut(&mut self) -> Vec<&mut super::Annotation> {
+        let entity_types = self.entity_types.iter_mut().flat_map(|entity_type| {
+            let properties = entity_type.properties.iter_mut();
+            let navigation = entity_type.navigation_properties.iter_mut();
+
+            entity_type
+                .annotations
+                .iter_mut()
+                .chain(properties.flat_map(|x| x.annotations.iter_mut()))
+                .chain(navigation.flat_map(|x| x.annotations.iter_mut()))
+        });
+
+        let complex_types = self.complex_types.iter_mut().flat_map(|complex_type| {
+            let properties = complex_type.properties.iter_mut();
+            let navigation = complex_type.navigation_properties.iter_mut();
+
+            complex_type
+                .annotations
+                .iter_mut()
+                .chain(properties.flat_map(|x| x.annotations.iter_mut()))
+                .chain(navigation.flat_map(|x| x.annotations.iter_mut()))
+        });
+
+        let functions = self.functions.iter_mut().flat_map(|function| {
+            let parameters = function.parameters.iter_mut();
+            let parameters = parameters.flat_map(|x| x.annotations.iter_mut());
+            function.annotations.iter_mut().chain(parameters)
+        });
+
+        let actions = self.actions.iter_mut().flat_map(|action| {
+            let parameters = action.parameters.iter_mut();
+            let parameters = parameters.flat_map(|x| x.annotations.iter_mut());
+            action.annotations.iter_mut().chain(parameters)
+        });
+
+        let container = &mut self.entity_container;
+        let entity_sets = container.entity_sets.iter_mut();
+        let singletons = container.singletons.iter_mut();
+        let function_imports = container.function_imports.iter_mut();
+        let action_imports = container.action_imports.iter_mut();
+        let out_of_line = self.annotations.iter_mut();
+
+        entity_types
+            .chain(complex_types)
+            .chain(functions)
+            .chain(actions)
+            .chain(entity_sets.flat_map(|x| x.annotations.iter_mut()))
+            .chain(singletons.flat_map(|x| x.annotations.iter_mut()))
+            .chain(function_imports.flat_map(|x| x.annotations.iter_mut()))
+            .chain(action_imports.flat_map(|x| x.annotations.iter_mut()))
+            .chain(out_of_line.flat_map(|x| x.annotations.iter_mut()))
+            .collect()
+    }
+
+    /// Every other reference to a named type in this schema: base types, the types of entity sets,
+    /// and the underlying types of enums and type definitions.
+    pub fn qualified_types_mut(&mut self) -> Vec<&mut super::QualifiedType> {
+        let entity_types = self.entity_types.iter_mut();
+        let complex_types = self.complex_types.iter_mut();
+        let enum_types = self.enum_types.iter_mut();
+        let type_definitions = self.type_definitions.iter_mut();
+        let entity_sets = self.entity_container.entity_sets.iter_mut();
+
+        entity_types
+            .filter_map(|entity_type| entity_type.base_type.as_mut())
+            .chain(complex_types.filter_map(|x| x.base_type.as_mut()))
+            .chain(enum_types.filter_map(|x| x.underlying_type.as_mut()))
+            .chain(type_definitions.map(|x| &mut x.underlying_type))
+            .chain(entity_sets.map(|x| &mut x.entity_type))
+            .collect()
+    }
+
     /// Look up a complex type by name within this schema.
     pub fn complex_type(&self, name: &str) -> Option<super::ComplexType> {
         self.complex_types
@@ -70,23 +196,22 @@ impl Schema {
             .cloned()
     }
 
-    /// Look up a function by name within this schema.
-    /// @TODO: we should really be looking this up in the entity container to check it is exposed
-    ///        in the API, and _then_ get the content.
-    pub fn function(&self, name: &str) -> Option<super::Function> {
+    /// Look up every overload of an unbound function by name within this schema. Unbound
+    /// functions can be overloaded, as long as each overload has a different set of parameters.
+    pub fn unbound_functions(&self, name: &str) -> Vec<super::Function> {
         self.functions
             .iter()
-            .find(|function| function.name == name)
+            .filter(|function| !function.is_bound && function.name == name)
             .cloned()
+            .collect()
     }
 
-    /// Look up an action by name within this schema.
-    /// @TODO: we should really be looking this up in the entity container to check it is exposed
-    ///        in the API, and _then_ get the content.
-    pub fn action(&self, name: &str) -> Option<super::Action> {
+    /// Look up an unbound action by name within this schema. Unlike functions, unbound actions
+    /// can't be overloaded.
+    pub fn unbound_action(&self, name: &str) -> Option<super::Action> {
         self.actions
             .iter()
-            .find(|action| action.name == name)
+            .find(|action| !action.is_bound && action.name == name)
             .cloned()
     }
 }
@@ -4,6 +4,7 @@ use pest::Parser;
 use pest_derive::Parser;
 use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 
 /// A complex type is the analogous to an NDC object type: it is a product type comprised of named
 /// fields that we'll represent using JSON objects. They may be built by extending other types.
@@ -16,6 +17,18 @@ pub struct ComplexType {
     #[serde(rename = "@BaseType")]
     pub base_type: Option<super::QualifiedType>,
 
+    /// Abstract complex types can't be instantiated, only extended.
+    #[serde(default)]
+    #[serde(rename = "@Abstract")]
+    #[serde(deserialize_with = "super::helpers::str_to_bool")]
+    pub is_abstract: bool,
+
+    /// Open complex types may have properties that aren't declared in the metadata.
+    #[serde(default)]
+    #[serde(rename = "@OpenType")]
+    #[serde(deserialize_with = "super::helpers::str_to_bool")]
+    pub is_open: bool,
+
     #[serde(default)]
     #[serde(rename = "Property")]
     pub properties: Vec<Property>,
@@ -23,60 +36,32 @@ pub struct ComplexType {
     #[serde(default)]
     #[serde(rename = "NavigationProperty")]
     pub navigation_properties: Vec<NavigationProperty>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
 
 impl ComplexType {
     /// Get all the fields from the type and the chain of base types.
     pub fn fields(&self, metadata: &super::EDMX) -> Vec<super::Property> {
-        let mut collection = Vec::new();
-        collection.append(&mut self.properties.clone());
-
-        if let Some(target) = self
-            .base_type
-            .as_ref()
-            .and_then(|x| metadata.entity_type(x))
-        {
-            collection.append(&mut target.fields(metadata))
-        }
-
-        if let Some(target) = self
-            .base_type
-            .as_ref()
-            .and_then(|x| metadata.complex_type(x))
-        {
-            collection.append(&mut target.fields(metadata))
-        }
-
-        collection
+        let mut fields = self.properties.clone();
+        fields.append(&mut metadata.inherited_properties(self.base_type.as_ref()));
+        fields
     }
 
     /// Get all the navigation properties from the type and the chain of base types.
     pub fn navigation_properties(&self, metadata: &super::EDMX) -> Vec<super::NavigationProperty> {
-        let mut collection = Vec::new();
-        collection.append(&mut self.navigation_properties.clone());
-
-        if let Some(target) = &self
-            .base_type
-            .as_ref()
-            .and_then(|x| metadata.entity_type(x))
-        {
-            collection.append(&mut target.navigation_properties(metadata))
-        }
-
-        if let Some(target) = &self
-            .base_type
-            .as_ref()
-            .and_then(|x| metadata.complex_type(x))
-        {
-            collection.append(&mut target.navigation_properties(metadata))
-        }
-
-        collection
+        let mut navigation_properties = self.navigation_properties.clone();
+        navigation_properties
+            .append(&mut metadata.inherited_navigation_properties(self.base_type.as_ref()));
+        navigation_properties
     }
 }
 
 /// An available navigation property. Entity sets may choose to bind this property to a different
-/// entity set to indicate a relationship between the two. This is analogous to foreign keys.
+/// entity set to indicate a relationship between the two. Where the navigation property has
+/// referential constraints, these tell us the foreign key behind the relationship.
 #[derive(Clone, Debug, Deserialize)]
 pub struct NavigationProperty {
     #[serde(rename = "@Name")]
@@ -84,6 +69,40 @@ pub struct NavigationProperty {
 
     #[serde(flatten)]
     pub r#type: super::TypeData,
+
+    /// Contained entities have no entity set of their own: they live beneath their parent entity,
+    /// as in `/People('x')/Trips`.
+    #[serde(default)]
+    #[serde(rename = "@ContainsTarget")]
+    #[serde(deserialize_with = "super::helpers::str_to_bool")]
+    pub contains_target: bool,
+
+    #[serde(default)]
+    #[serde(rename = "ReferentialConstraint")]
+    pub referential_constraints: Vec<ReferentialConstraint>,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
+}
+
+/// A pair of properties that a navigation property relates: the `property` on the dependent
+/// entity (the one with the navigation property) holds the value of the `referenced_property` on
+/// the principal entity (its target). In RDBMS terms, this is a foreign key column.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ReferentialConstraint {
+    #[serde(rename = "@Property")]
+    pub property: String,
+
+    #[serde(rename = "@ReferencedProperty")]
+    pub referenced_property: String,
+}
+
+impl NavigationProperty {
+    /// Does this property lead to many entities (`Collection(T)`) rather than a single one?
+    pub fn is_collection(&self) -> bool {
+        matches!(self.r#type.inner, Type::Collection { .. })
+    }
 }
 
 /// A field within a complex type: it is described by a field name and a value type.
@@ -94,6 +113,10 @@ pub struct Property {
 
     #[serde(flatten)]
     pub r#type: TypeData,
+
+    #[serde(default)]
+    #[serde(rename = "Annotation")]
+    pub annotations: Vec<super::Annotation>,
 }
 
 impl Property {
@@ -107,8 +130,8 @@ impl Property {
 }
 
 /// As well as an underlying type, some number of other attributes relating to the type may be
-/// included as attributes on the parent tag (`Property`, `Parameter`, and so on). Currently,
-/// because we perform no mutations, the only one we really care about is nullability.
+/// included as attributes on the parent tag (`Property`, `Parameter`, and so on): nullability,
+/// and the facets that constrain the type's values.
 #[derive(Clone, Debug, Deserialize)]
 pub struct TypeData {
     #[serde(rename = "@Type")]
@@ -118,6 +141,62 @@ pub struct TypeData {
     #[serde(rename = "@Nullable")]
     #[serde(deserialize_with = "super::helpers::str_to_bool")]
     pub nullable: bool,
+
+    #[serde(flatten)]
+    pub facets: Facets,
+}
+
+/// Facets refine a primitive type: a string's maximum length, or a decimal's precision and scale.
+/// Properties can also declare a default value. We keep their values as strings, because some
+/// facets have symbolic values such as `max` or `variable`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Facets {
+    #[serde(rename = "@MaxLength")]
+    pub max_length: Option<String>,
+
+    #[serde(rename = "@Precision")]
+    pub precision: Option<String>,
+
+    #[serde(rename = "@Scale")]
+    pub scale: Option<String>,
+
+    #[serde(rename = "@SRID")]
+    pub srid: Option<String>,
+
+    /// The value that the service uses when a property is omitted, written as an OData literal.
+    #[serde(rename = "@DefaultValue")]
+    pub default_value: Option<String>,
+}
+
+impl Facets {
+    /// Fill in any facets that we don't have from another set of facets. Facets on a property
+    /// take precedence over the facets of its type definition.
+    pub fn or(self, fallback: &Facets) -> Facets {
+        Facets {
+            max_length: self.max_length.or_else(|| fallback.max_length.clone()),
+            precision: self.precision.or_else(|| fallback.precision.clone()),
+            scale: self.scale.or_else(|| fallback.scale.clone()),
+            srid: self.srid.or_else(|| fallback.srid.clone()),
+            default_value: self
+                .default_value
+                .or_else(|| fallback.default_value.clone()),
+        }
+    }
+}
+
+/// A type definition gives a new name to a primitive type, along with some facets: for example,
+/// `Trippin.Email` might be an `Edm.String` with a `MaxLength`. Type definitions don't add
+/// anything that the NDC could use, so we replace references to them with their underlying types.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TypeDefinition {
+    #[serde(rename = "@Name")]
+    pub name: String,
+
+    #[serde(rename = "@UnderlyingType")]
+    pub underlying_type: QualifiedType,
+
+    #[serde(flatten)]
+    pub facets: Facets,
 }
 
 impl TypeData {
@@ -128,6 +207,16 @@ impl TypeData {
     pub fn underlying_type(&self) -> &QualifiedType {
         self.inner.underlying_type()
     }
+
+    /// If the underlying type is a type definition, replace it with the primitive type it names,
+    /// and take on any of its facets that we don't already have.
+    pub fn resolve(&mut self, type_definitions: &BTreeMap<String, TypeDefinition>) {
+        if let Some(type_definition) = type_definitions.get(&self.underlying_type().to_string()) {
+            self.inner
+                .replace_underlying_type(type_definition.underlying_type.clone());
+            self.facets = std::mem::take(&mut self.facets).or(&type_definition.facets);
+        }
+    }
 }
 
 /// If we ignore nullability  - in OData, this is a feature of the user of the type, not the type
@@ -150,6 +239,14 @@ impl Type {
             Type::Qualified { qualified_type } => qualified_type,
         }
     }
+
+    /// Swap out the underlying type, keeping any 'Collection' layers around it.
+    pub fn replace_underlying_type(&mut self, replacement: QualifiedType) {
+        match self {
+            Type::Collection { elements } => elements.replace_underlying_type(replacement),
+            Type::Qualified { qualified_type } => *qualified_type = replacement,
+        }
+    }
 }
 
 impl std::fmt::Display for Type {
@@ -179,7 +276,7 @@ impl Serialize for Type {
 }
 
 /// Specifically, a scalar type (i.e. not a collection) that belongs to some schema.
-#[derive(Clone, Debug, JsonSchema)]
+#[derive(Clone, Debug, Eq, JsonSchema, PartialEq)]
 pub struct QualifiedType {
     pub schema: String,
     pub name: String,
@@ -231,10 +328,16 @@ fn read_type_pairs<'de, D: Deserializer<'de>>(
 
             Rule::component => components.push(pair.as_str()),
 
+            // The grammar anchors the whole type string, so we'll see the end of the input.
+            Rule::EOI => {}
+
             // I'm not sure why `pest` generates these rules, given that they're explicitly
             // silenced in the grammar...
             Rule::qualified_name => panic!("Internal error: found raw qualified name"),
             Rule::type_name => panic!("Internal error: found raw type name"),
+            Rule::identifier_start | Rule::identifier_character => {
+                panic!("Internal error: found raw identifier character")
+            }
         }
     }
 
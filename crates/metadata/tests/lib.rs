@@ -0,0 +1,107 @@
+use metadata::{ndc, odata};
+
+/// Prepare the schema from one of the metadata documents in the `goldenfiles` directory.
+fn schema(name: &str, parse: impl Fn(&str) -> odata::EDMX) -> ndc::Schema {
+    let root = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let content = std::fs::read_to_string(root.join("tests/goldenfiles").join(name)).unwrap();
+
+    metadata::prepare_odata_edmx(parse(&content))
+}
+
+#[test]
+fn test_xml_schema() {
+    let schema = schema("trippin.xml", |content| {
+        quick_xml::de::from_str(content).unwrap()
+    });
+
+    insta::assert_json_snapshot!("trippin", schema);
+}
+
+// Both forms of the document describe the same service, so they should produce the same schema,
+// and thus the same snapshot.
+#[test]
+fn test_json_schema() {
+    let schema = schema("trippin.json", |content| {
+        odata::json::from_str(content).unwrap()
+    });
+
+    insta::assert_json_snapshot!("trippin", schema);
+}
+
+// Validation reports every problem with a broken document, where we'd otherwise have panicked.
+#[test]
+fn test_validation() {
+    let schema = schema("broken.xml", |content| {
+        quick_xml::de::from_str(content).unwrap()
+    });
+
+    let problems: Vec<String> = ndc::validation::validate(&schema)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    insta::assert_json_snapshot!("broken", problems);
+}
+
+// The collections of derived types and of functions are qualified when their usual names are
+// already taken.
+#[test]
+fn test_collection_name_clashes() {
+    let schema = schema("clashes.xml", |content| {
+        quick_xml::de::from_str(content).unwrap()
+    });
+
+    let names: Vec<&str> = schema
+        .collections
+        .iter()
+        .map(|collection| collection.name.as_str())
+        .collect();
+
+    insta::assert_json_snapshot!("clashes", names);
+}
+
+// Read-only properties, immutable ones included, can't be set on insert or on update, except for
+// computed keys.
+#[test]
+fn test_read_only_properties() {
+    let schema = schema("read_only.xml", |content| {
+        quick_xml::de::from_str(content).unwrap()
+    });
+
+    let field_names =
+        |name: &str| -> Vec<&String> { schema.object_types[name].fields.keys().collect() };
+
+    let fields = serde_json::json!({
+        "insert": field_names("A.Order_insert"),
+        "update": field_names("A.Order_update"),
+    });
+
+    insta::assert_json_snapshot!("read_only", fields);
+}
+
+// Navigation property bindings can target entity sets in their own container, or in any other
+// container by its qualified (or aliased) name. We only link through the bindings that the
+// navigation restrictions let us navigate and update.
+#[test]
+fn test_binding_targets() {
+    let schema = schema("bindings.xml", |content| {
+        quick_xml::de::from_str(content).unwrap()
+    });
+
+    let relationships: std::collections::BTreeMap<&str, _> = schema
+        .collections
+        .iter()
+        .map(|collection| (collection.name.as_str(), &collection.relationships))
+        .collect();
+
+    let procedures: Vec<&str> = schema
+        .procedures
+        .iter()
+        .map(|procedure| procedure.name.as_str())
+        .collect();
+
+    insta::assert_json_snapshot!(
+        "bindings",
+        serde_json::json!({ "relationships": relationships, "procedures": procedures })
+    );
+}
@@ -1,56 +1,125 @@
 //! Interpreters and generators for configuration in `ndc-odata`.
 
+pub mod documents;
+
 use metadata::ndc;
 use ndc_sdk::connector;
 
-/// Take the endpoint URL from the user config, introspect the schema, and replace the rest of the
+/// Introspect the schema (along with any schemas it references), and replace the rest of the
 /// metadata with the results of that introspection. Most of the work of this function is farmed
 /// out to the `metadata` package.
 pub async fn update_configuration(
     configuration: ndc::RawConfiguration,
 ) -> Result<ndc::RawConfiguration, connector::UpdateConfigurationError> {
-    let metadata = format!("{}/$metadata", configuration.api_endpoint);
-
-    // @TODO: can we do something about error unification?
-    let response = reqwest::get(&metadata)
+    let document = introspect(&configuration)
         .await
-        .map_err(Box::from)
         .map_err(connector::UpdateConfigurationError::Other)?;
 
-    let response_text = response
-        .text()
-        .await
-        .map_err(Box::from)
-        .map_err(connector::UpdateConfigurationError::Other)?;
+    let schema = metadata::prepare_odata_edmx(document);
 
-    let document = quick_xml::de::from_str(&response_text)
-        .map_err(Box::from)
-        .map_err(connector::UpdateConfigurationError::Other)?;
+    // Broken metadata would only fail later, when a request touches the broken part, so we refuse
+    // it now, and say what's wrong with it.
+    let invalid_ranges = ndc::validation::validate(&schema);
+
+    if !invalid_ranges.is_empty() {
+        let problems: Vec<String> = invalid_ranges.iter().map(ToString::to_string).collect();
+        let message = format!("The metadata is invalid:\n{}", problems.join("\n"));
+
+        return Err(connector::UpdateConfigurationError::Other(message.into()));
+    }
 
     Ok(ndc::RawConfiguration {
         api_endpoint: configuration.api_endpoint.clone(),
-        schema: metadata::prepare_odata_edmx(document),
+        metadata_path: configuration.metadata_path,
+        metadata_document: configuration.metadata_document,
+        schema,
+        dynamic_properties: configuration.dynamic_properties,
     })
 }
 
-/// The only thing we actually care about is whether we can parse the URL. Everything else we
-/// currently take on good faith.
-/// @TODO: this would be a good place to check a whole bunch of invariants:
-///        - Do all entity sets talk about valid entity types?
-///        - Do all entity types have a key somewhere in their ancestry?
-///        - etc.
+/// Find the metadata document. An inline document wins, then a local file, and otherwise we ask
+/// the endpoint for its `/$metadata`. Relative references in an inline document are resolved
+/// against the endpoint, as though that's where it came from.
+async fn introspect(
+    configuration: &ndc::RawConfiguration,
+) -> Result<metadata::odata::EDMX, documents::Error> {
+    let endpoint = documents::Location::Url(format!("{}/$metadata", configuration.api_endpoint));
+
+    match (
+        &configuration.metadata_document,
+        &configuration.metadata_path,
+    ) {
+        (Some(contents), _) => {
+            let document = documents::parse(contents)
+                .map_err(|error| format!("Couldn't parse metadata_document: {}", error))?;
+
+            documents::include_references(&endpoint, document).await
+        }
+
+        (None, Some(path)) => documents::load(documents::Location::File(path.into())).await,
+        (None, None) => documents::load(endpoint).await,
+    }
+}
+
+/// Check that we can parse the endpoint URL, that the declared dynamic properties belong to open
+/// types, and that everything the schema refers to exists. We report every problem we find, rather
+/// than only the first.
 pub async fn validate_raw_configuration(
     configuration: ndc::RawConfiguration,
 ) -> Result<ndc::Configuration, connector::ValidateError> {
-    let parsed = ndc::Endpoint::parse(&configuration.api_endpoint).map_err(|message| {
-        let path = Vec::from([connector::KeyOrIndex::Key("api_endpoint".to_string())]);
-        let invalid_range = connector::InvalidRange { path, message };
+    let mut invalid_ranges = Vec::new();
 
-        connector::ValidateError::ValidateError(Vec::from([invalid_range]))
-    })?;
+    let parsed = ndc::Endpoint::parse(&configuration.api_endpoint)
+        .map_err(|message| {
+            let path = Vec::from([connector::KeyOrIndex::Key("api_endpoint".to_string())]);
+            invalid_ranges.push(connector::InvalidRange { path, message });
+        })
+        .ok();
 
-    Ok(metadata::ndc::Configuration {
-        api_endpoint: parsed,
-        schema: configuration.schema,
-    })
+    // Declared dynamic properties become columns of their open types, just like any other.
+    let mut schema = configuration.schema;
+
+    for (object_type, dynamic_properties) in &configuration.dynamic_properties {
+        if let Err(message) = ndc::open_types::declare(&mut schema, object_type, dynamic_properties)
+        {
+            let path = Vec::from([
+                connector::KeyOrIndex::Key("dynamic_properties".to_string()),
+                connector::KeyOrIndex::Key(object_type.clone()),
+            ]);
+
+            invalid_ranges.push(connector::InvalidRange { path, message });
+        }
+    }
+
+    invalid_ranges.extend(
+        ndc::validation::validate(&schema)
+            .into_iter()
+            .map(invalid_range),
+    );
+
+    match parsed {
+        Some(api_endpoint) if invalid_ranges.is_empty() => Ok(metadata::ndc::Configuration {
+            api_endpoint,
+            schema,
+        }),
+
+        _ => Err(connector::ValidateError::ValidateError(invalid_ranges)),
+    }
+}
+
+/// Translate a problem that the `metadata` package found into the SDK's terms.
+fn invalid_range(invalid_range: ndc::validation::InvalidRange) -> connector::InvalidRange {
+    let path = invalid_range
+        .path
+        .into_iter()
+        .map(|step| match step {
+            ndc::validation::KeyOrIndex::Key(key) => connector::KeyOrIndex::Key(key),
+            ndc::validation::KeyOrIndex::Index(index) => connector::KeyOrIndex::Index(index),
+        })
+        .collect();
+
+    connector::InvalidRange {
+        path,
+        message: invalid_range.message,
+    }
 }
@@ -0,0 +1,153 @@
+//! Loading metadata documents, along with every document that they reference.
+
+use metadata::odata;
+use reqwest::header::ACCEPT;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Where a metadata document lives. References within a document may be relative, so we need to
+/// know where the referencing document came from in order to find them.
+#[derive(Clone, Debug)]
+pub enum Location {
+    Url(String),
+    File(PathBuf),
+}
+
+impl Location {
+    /// Find the location of a document referenced from this one.
+    pub fn resolve(&self, uri: &str) -> Location {
+        if let Some(path) = uri.strip_prefix("file://") {
+            return Location::File(PathBuf::from(path));
+        }
+
+        if uri.starts_with("http://") || uri.starts_with("https://") {
+            return Location::Url(uri.to_string());
+        }
+
+        match self {
+            // An absolute path is relative to the origin, and anything else is relative to the
+            // "directory" of the referencing document.
+            Location::Url(url) => {
+                let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+
+                let base = match uri.starts_with('/') {
+                    true => rest.split('/').next().unwrap_or(rest),
+                    false => rest
+                        .rsplit_once('/')
+                        .map_or(rest, |(directory, _)| directory),
+                };
+
+                Location::Url(format!(
+                    "{}://{}/{}",
+                    scheme,
+                    base,
+                    uri.trim_start_matches('/')
+                ))
+            }
+
+            Location::File(path) => Location::File(match path.parent() {
+                Some(directory) => directory.join(uri),
+                None => PathBuf::from(uri),
+            }),
+        }
+    }
+
+    /// Fetch the document's contents. We ask services for JSON CSDL where they have it, and fall
+    /// back to asking for XML if they refuse.
+    pub async fn read(&self) -> Result<String, Error> {
+        match self {
+            Location::Url(url) => {
+                let client = reqwest::Client::new();
+
+                let response = client
+                    .get(url)
+                    .header(ACCEPT, "application/json, application/xml;q=0.9")
+                    .send()
+                    .await?;
+
+                if response.status().is_success() {
+                    return Ok(response.text().await?);
+                }
+
+                let response = client
+                    .get(url)
+                    .header(ACCEPT, "application/xml")
+                    .send()
+                    .await?;
+
+                Ok(response.error_for_status()?.text().await?)
+            }
+
+            Location::File(path) => Ok(std::fs::read_to_string(path)?),
+        }
+    }
+
+    /// Fetch and parse the document, in whichever format it's written.
+    pub async fn parse(&self) -> Result<odata::EDMX, Error> {
+        let contents = self.read().await?;
+        parse(&contents).map_err(|error| format!("Couldn't parse {}: {}", self, error).into())
+    }
+}
+
+/// Parse a metadata document. JSON CSDL documents are objects, and XML documents are not, so the
+/// first character tells us which we have.
+pub fn parse(contents: &str) -> Result<odata::EDMX, Error> {
+    match contents.trim_start().starts_with('{') {
+        true => Ok(odata::json::from_str(contents)?),
+        false => Ok(quick_xml::de::from_str(contents)?),
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Url(url) => write!(formatter, "{}", url),
+            Location::File(path) => write!(formatter, "{}", path.display()),
+        }
+    }
+}
+
+/// Load a metadata document, and include the schemas of every document that it references,
+/// transitively. Each document resolves its own aliases before we include it, as its aliases
+/// aren't in scope anywhere else.
+pub async fn load(location: Location) -> Result<odata::EDMX, Error> {
+    let document = location.parse().await?;
+    include_references(&location, document).await
+}
+
+/// Include the schemas of every document that a document references, transitively. The location
+/// is where the document came from (or, for a document that we were given directly, where it
+/// would have come from), against which relative references are resolved.
+pub async fn include_references(
+    location: &Location,
+    mut document: odata::EDMX,
+) -> Result<odata::EDMX, Error> {
+    let mut pending = references(location, &document);
+    let mut visited = BTreeSet::from([location.to_string()]);
+
+    while let Some((target, reference)) = pending.pop() {
+        if !visited.insert(target.to_string()) {
+            continue;
+        }
+
+        let mut referenced = target.parse().await?;
+
+        referenced.resolve_aliases();
+        pending.extend(references(&target, &referenced));
+        document.include(&reference, referenced);
+    }
+
+    Ok(document)
+}
+
+/// The documents that a document references, other than the standard vocabularies.
+fn references(location: &Location, document: &odata::EDMX) -> Vec<(Location, odata::Reference)> {
+    document
+        .references
+        .iter()
+        .filter(|reference| !reference.is_vocabulary())
+        .map(|reference| (location.resolve(&reference.uri), reference.clone()))
+        .collect()
+}
@@ -9,9 +9,24 @@ pub fn get_details(
     configuration: &ndc::Configuration,
     request: models::QueryRequest,
 ) -> Result<models::ExplainResponse, connector::ExplainError> {
+    super::query::restrictions::check(
+        configuration,
+        &request.collection_relationships,
+        &request.collection,
+        &request.query,
+    )
+    .map_err(connector::ExplainError::UnsupportedOperation)?;
+
+    super::query::validation::check(
+        configuration,
+        &request.collection_relationships,
+        &request.collection,
+        &request.query,
+    )
+    .map_err(connector::ExplainError::InvalidRequest)?;
+
     let request_url = super::query::Request::from_user_request(configuration, &request)
-        .map_err(Box::from)
-        .map_err(connector::ExplainError::Other)?
+        .map_err(connector::ExplainError::InvalidRequest)?
         .to_url()
         .map_err(Box::from)
         .map_err(connector::ExplainError::Other)?;
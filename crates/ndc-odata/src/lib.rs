@@ -2,6 +2,7 @@ mod capabilities;
 mod configuration;
 mod explain;
 mod health_check;
+mod mutation;
 mod query;
 mod schema;
 
@@ -73,11 +74,13 @@ impl connector::Connector for OData {
     }
 
     async fn mutation(
-        _configuration: &Self::Configuration,
+        configuration: &Self::Configuration,
         _state: &Self::State,
-        _query: models::MutationRequest,
+        request: models::MutationRequest,
     ) -> Result<JsonResponse<models::MutationResponse>, connector::MutationError> {
-        todo!()
+        Ok(mutation::execute_mutation(configuration, request)
+            .await?
+            .into())
     }
 
     async fn query(
@@ -1,7 +1,90 @@
+use clap::{Parser, Subcommand};
 use ndc_odata::OData;
+use ndc_sdk::connector::Connector;
 use ndc_sdk::default_main::default_main;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// We add our own `introspect` command to the SDK's. Anything else, including the SDK's own help,
+/// goes to the SDK untouched, which parses the arguments for itself.
+#[derive(Parser)]
+#[command(
+    disable_help_flag = true,
+    disable_version_flag = true,
+    disable_help_subcommand = true,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The arguments for the SDK, such as `serve --configuration ...` or `--help`.
+    #[arg(hide = true, allow_hyphen_values = true, trailing_var_arg = true)]
+    arguments: Vec<OsString>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    Introspect(Introspect),
+}
+
+/// Introspect a local metadata document, rather than the service's `/$metadata`, and write the
+/// resulting configuration. Everything else in the configuration is kept as it was.
+#[derive(clap::Args)]
+struct Introspect {
+    /// Print help.
+    #[arg(short, long, action = clap::ArgAction::Help)]
+    help: (),
+
+    /// The metadata document, in XML or JSON.
+    #[arg(long)]
+    from_file: PathBuf,
+
+    /// The configuration to update. If this is missing, we start from an empty configuration and
+    /// write the result to stdout.
+    #[arg(long)]
+    configuration: Option<PathBuf>,
+}
 
 #[tokio::main]
 pub async fn main() {
-    default_main::<OData>().await.unwrap()
+    match Cli::parse().command {
+        Some(Command::Introspect(arguments)) => {
+            if let Err(error) = introspect(arguments).await {
+                eprintln!("Couldn't introspect the metadata document: {error}");
+                std::process::exit(1);
+            }
+        }
+
+        None => default_main::<OData>().await.unwrap(),
+    }
+}
+
+async fn introspect(arguments: Introspect) -> Result<(), Box<dyn std::error::Error>> {
+    let configuration = match &arguments.configuration {
+        Some(path) if path.exists() => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+        _ => OData::make_empty_configuration(),
+    };
+
+    // The file is only for this introspection, so we don't save it in the configuration.
+    let from_file = metadata::ndc::RawConfiguration {
+        metadata_path: Some(arguments.from_file.display().to_string()),
+        metadata_document: None,
+        ..configuration.clone()
+    };
+
+    let updated = metadata::ndc::RawConfiguration {
+        metadata_path: configuration.metadata_path,
+        metadata_document: configuration.metadata_document,
+        ..OData::update_configuration(from_file).await?
+    };
+
+    let output = serde_json::to_string_pretty(&updated)?;
+
+    match &arguments.configuration {
+        Some(path) => std::fs::write(path, output + "\n")?,
+        None => println!("{output}"),
+    }
+
+    Ok(())
 }
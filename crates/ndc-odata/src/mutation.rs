@@ -0,0 +1,241 @@
+//! The handler for the `/mutation` endpoint.
+
+pub mod actions;
+pub mod batch;
+pub mod collections;
+pub mod references;
+pub mod singletons;
+
+pub use batch::*;
+
+use indexmap::IndexMap;
+use metadata::ndc;
+use ndc_sdk::{connector, models};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Run each operation in the mutation request in turn. OData doesn't give us a way to make a
+/// sequence of arbitrary operations transactional, so we stop at the first failure.
+pub async fn execute_mutation(
+    configuration: &ndc::Configuration,
+    request: models::MutationRequest,
+) -> Result<models::MutationResponse, connector::MutationError> {
+    let mut operation_results = Vec::new();
+
+    for operation in &request.operations {
+        let models::MutationOperation::Procedure {
+            name,
+            arguments,
+            fields,
+        } = operation;
+
+        let procedure = configuration.schema.procedure(name).ok_or_else(|| {
+            connector::MutationError::InvalidRequest(format!("Unknown procedure: {name}"))
+        })?;
+
+        // Catch invalid arguments before we call the service, so we can say which one is wrong.
+        for (argument, value) in arguments {
+            if let Some(argument_type) = procedure.arguments.get(argument) {
+                crate::query::validation::check_value(
+                    configuration,
+                    argument,
+                    argument_type,
+                    procedure.argument_constraints.get(argument),
+                    value,
+                )
+                .map_err(connector::MutationError::InvalidRequest)?;
+            }
+        }
+
+        let outcome: Outcome = match &procedure.operation {
+            ndc::Operation::Action { action, binding } => Outcome {
+                affected_rows: 1,
+                result: actions::invoke(
+                    configuration,
+                    procedure,
+                    action,
+                    binding.as_ref(),
+                    arguments,
+                )
+                .await?,
+            },
+
+            ndc::Operation::InsertMany { collection } => {
+                collections::insert_many(configuration, collection, arguments)
+                    .await?
+                    .into()
+            }
+
+            ndc::Operation::UpdateMany { collection } => {
+                collections::update_many(configuration, collection, arguments)
+                    .await?
+                    .into()
+            }
+
+            ndc::Operation::DeleteMany { collection } => {
+                collections::delete_many(configuration, collection, arguments)
+                    .await?
+                    .into()
+            }
+
+            ndc::Operation::UpdateSingleton { singleton } => {
+                singletons::update(configuration, singleton, arguments)
+                    .await?
+                    .into()
+            }
+
+            ndc::Operation::Link {
+                collection,
+                navigation,
+            } => references::link(configuration, collection, navigation, arguments)
+                .await?
+                .into(),
+
+            ndc::Operation::Unlink {
+                collection,
+                navigation,
+            } => references::unlink(configuration, collection, navigation, arguments)
+                .await?
+                .into(),
+
+            ndc::Operation::SetLink {
+                collection,
+                navigation,
+            } => references::set_link(configuration, collection, navigation, arguments)
+                .await?
+                .into(),
+        };
+
+        operation_results.push(models::MutationOperationResults {
+            affected_rows: outcome.affected_rows,
+            returning: Some(Vec::from([select(&outcome.result, fields)?])),
+        });
+    }
+
+    Ok(models::MutationResponse { operation_results })
+}
+
+/// The result of one of the generated procedures: the number of entities it touched.
+pub struct MutationResult {
+    pub affected_rows: u32,
+}
+
+/// What a procedure did: how many entities it affected, and the value it returned.
+pub struct Outcome {
+    pub affected_rows: u32,
+    pub result: Value,
+}
+
+impl From<MutationResult> for Outcome {
+    /// The generated procedures return an object of the `OData.MutationResult` type.
+    fn from(result: MutationResult) -> Self {
+        Outcome {
+            affected_rows: result.affected_rows,
+            result: Value::Object(Map::from_iter([(
+                "affected_rows".to_string(),
+                Value::from(result.affected_rows),
+            )])),
+        }
+    }
+}
+
+/// Look up a required argument to a procedure.
+pub fn argument<'a>(
+    arguments: &'a BTreeMap<String, Value>,
+    name: &str,
+) -> Result<&'a Value, connector::MutationError> {
+    arguments.get(name).ok_or_else(|| {
+        connector::MutationError::InvalidRequest(format!("Missing argument: {name}"))
+    })
+}
+
+/// The characters that we leave alone in resource paths: the unreserved characters, the `/` between
+/// segments, and the delimiters of OData's own path syntax, such as the quotes and parentheses of
+/// `People('a')` or the `$` of `$filter(...)`. Everything else in a literal, such as `#`, `?`, `&`,
+/// or `+`, would change the meaning of the URL, so we percent-encode it.
+const PATH: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'/')
+    .remove(b'$')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b',')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@');
+
+/// Percent-encode a resource path, such as `People('a#b')/Friends`, so that the literals within it
+/// reach the service intact.
+pub fn encode_path(path: &str) -> String {
+    utf8_percent_encode(path, PATH).to_string()
+}
+
+/// Check that the service accepted our request, and return the body of its response if it did.
+/// Client errors mean that the service understood the request but refused it, so we report them
+/// as unprocessable along with whatever explanation the service gave us.
+pub async fn check(response: reqwest::Response) -> Result<String, connector::MutationError> {
+    let status = response.status();
+
+    let body = response
+        .text()
+        .await
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)?;
+
+    if status.is_success() {
+        Ok(body)
+    } else if status.is_client_error() {
+        Err(connector::MutationError::UnprocessableContent(body))
+    } else {
+        Err(connector::MutationError::Other(Box::from(format!(
+            "{status}: {body}"
+        ))))
+    }
+}
+
+/// Pick the requested fields out of a procedure's result, in the same way that we pick columns
+/// out of a row in a query. If no fields are requested, we return the whole result. Results that
+/// aren't objects are returned under the `__value` field, just as function results are.
+fn select(
+    result: &Value,
+    fields: &Option<IndexMap<String, models::Field>>,
+) -> Result<IndexMap<String, models::RowFieldValue>, connector::MutationError> {
+    let result = match result {
+        Value::Object(object) => object.clone(),
+        other => Map::from_iter([(crate::query::VALUE_FIELD.to_string(), other.clone())]),
+    };
+
+    let mut row = IndexMap::new();
+
+    match fields {
+        None => {
+            for (field, value) in &result {
+                row.insert(field.clone(), models::RowFieldValue(value.clone()));
+            }
+        }
+
+        Some(fields) => {
+            for (alias, field) in fields {
+                match field {
+                    models::Field::Column { column } => {
+                        let value = result.get(column).cloned().unwrap_or(Value::Null);
+                        row.insert(alias.clone(), models::RowFieldValue(value));
+                    }
+
+                    models::Field::Relationship { .. } => {
+                        return Err(connector::MutationError::UnsupportedOperation(
+                            "Relationships in procedure results are not yet supported.".to_string(),
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(row)
+}
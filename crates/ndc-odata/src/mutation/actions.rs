@@ -0,0 +1,69 @@
+//! Invoking OData actions as NDC procedures.
+
+use crate::query;
+use metadata::ndc;
+use ndc_sdk::connector;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Invoke an action by POSTing its non-binding arguments as a JSON object. Unbound actions live at
+/// the service root, and bound actions are invoked on the entities to which they're bound, as in
+/// `/People('russellwhyte')/Trippin.ShareTrip`.
+pub async fn invoke(
+    configuration: &ndc::Configuration,
+    procedure: &ndc::Procedure,
+    action: &str,
+    binding: Option<&ndc::Binding>,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<Value, connector::MutationError> {
+    let root = configuration.api_endpoint.to_string();
+
+    let path = match binding {
+        None => action.to_string(),
+        Some(binding) => format!(
+            "{}/{}",
+            query::functions::binding_path(configuration, binding, arguments)
+                .map_err(connector::MutationError::InvalidRequest)?,
+            binding.operation
+        ),
+    };
+
+    let mut body = Map::new();
+
+    for name in procedure.arguments.keys() {
+        if binding.is_some_and(|binding| &binding.parameter == name) {
+            continue;
+        }
+
+        if let Some(value) = arguments.get(name) {
+            body.insert(name.clone(), value.clone());
+        }
+    }
+
+    let response = reqwest::Client::new()
+        .post(format!("{root}/{}", super::encode_path(&path)))
+        .json(&body)
+        .send()
+        .await
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)?;
+
+    let response_body = super::check(response).await?;
+
+    // Actions without a return type respond with `204 No Content`, and we report that as a
+    // single affected entity.
+    if procedure.result_type.underlying_type() == &ndc::mutations::mutation_result_type() {
+        return Ok(super::Outcome::from(super::MutationResult { affected_rows: 1 }).result);
+    }
+
+    if response_body.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    let result: Map<String, Value> = serde_json::from_str(&response_body)
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)?;
+
+    let object = query::functions::returns_object(configuration, &procedure.result_type);
+    Ok(query::functions::unwrap_result(result, object))
+}
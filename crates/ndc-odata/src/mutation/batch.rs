@@ -0,0 +1,119 @@
+//! OData's `$batch` requests, which let us send many requests to the service at once. We use the
+//! multipart format, as it's the only one that 4.0 services are guaranteed to understand, and we
+//! put every request into a single change set so that the service applies them atomically.
+
+use ndc_sdk::connector;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Method;
+use serde_json::Value;
+
+const BATCH_BOUNDARY: &str = "batch_ndc_odata";
+const CHANGESET_BOUNDARY: &str = "changeset_ndc_odata";
+
+/// A batch of requests, all of which must succeed for the batch to succeed.
+#[derive(Default)]
+pub struct Batch {
+    pub parts: Vec<Part>,
+}
+
+/// A single request within a batch. The URL is relative to the service root, and we encode it when
+/// we render the batch.
+pub struct Part {
+    pub method: Method,
+    pub url: String,
+    pub body: Option<Value>,
+}
+
+impl Batch {
+    pub fn push(&mut self, method: Method, url: String, body: Option<Value>) {
+        self.parts.push(Part { method, url, body });
+    }
+
+    /// Render the batch as a `multipart/mixed` request body.
+    pub fn to_body(&self) -> String {
+        let mut body = String::new();
+
+        body.push_str(&format!("--{BATCH_BOUNDARY}\r\n"));
+        body.push_str(&format!(
+            "Content-Type: multipart/mixed; boundary={CHANGESET_BOUNDARY}\r\n\r\n"
+        ));
+
+        for (index, part) in self.parts.iter().enumerate() {
+            body.push_str(&format!("--{CHANGESET_BOUNDARY}\r\n"));
+            body.push_str("Content-Type: application/http\r\n");
+            body.push_str("Content-Transfer-Encoding: binary\r\n");
+            body.push_str(&format!("Content-ID: {}\r\n\r\n", index + 1));
+            body.push_str(&format!(
+                "{} {} HTTP/1.1\r\n",
+                part.method,
+                super::encode_path(&part.url)
+            ));
+
+            match &part.body {
+                Some(json) => {
+                    body.push_str("Content-Type: application/json\r\n\r\n");
+                    body.push_str(&format!("{json}\r\n"));
+                }
+
+                None => body.push_str("\r\n"),
+            }
+        }
+
+        body.push_str(&format!("--{CHANGESET_BOUNDARY}--\r\n"));
+        body.push_str(&format!("--{BATCH_BOUNDARY}--\r\n"));
+
+        body
+    }
+
+    /// Send the batch to the service, failing if any of the requests within it failed.
+    pub async fn send(
+        &self,
+        client: &reqwest::Client,
+        root: &str,
+    ) -> Result<(), connector::MutationError> {
+        if self.parts.is_empty() {
+            return Ok(());
+        }
+
+        let response = client
+            .post(format!("{root}/$batch"))
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/mixed; boundary={BATCH_BOUNDARY}"),
+            )
+            .body(self.to_body())
+            .send()
+            .await
+            .map_err(Box::from)
+            .map_err(connector::MutationError::Other)?;
+
+        let body = super::check(response).await?;
+        let statuses = statuses(&body);
+
+        // When a change set fails, the service answers it with a single error response.
+        if !statuses.iter().all(|status| (200..300).contains(status)) {
+            return Err(connector::MutationError::UnprocessableContent(body));
+        }
+
+        // Otherwise, we need a successful answer to every request before we can say that they all
+        // succeeded.
+        if statuses.len() < self.parts.len() {
+            return Err(connector::MutationError::Other(Box::from(format!(
+                "The service answered {} of the {} requests in the batch.",
+                statuses.len(),
+                self.parts.len()
+            ))));
+        }
+
+        Ok(())
+    }
+}
+
+/// Find the status codes of the responses within a multipart batch response. Each response is an
+/// `application/http` part whose body begins with a status line such as `HTTP/1.1 204 No Content`.
+pub fn statuses(body: &str) -> Vec<u16> {
+    body.lines()
+        .filter_map(|line| line.strip_prefix("HTTP/1.1 "))
+        .filter_map(|status| status.split_whitespace().next()?.parse().ok())
+        .collect()
+}
@@ -0,0 +1,315 @@
+//! The insert, update, and delete procedures that we generate for every collection. Where the
+//! service speaks OData 4.01, we can hand the whole operation to the service as a single request.
+//! Otherwise, we find the entities we need to change and send a batch of individual requests.
+
+use crate::query;
+use metadata::ndc;
+use ndc_sdk::{connector, models};
+use reqwest::Method;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Insert every object in the `objects` argument. In 4.01, we can `PATCH` the collection with a
+/// delta payload containing all the new entities.
+pub async fn insert_many(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<super::MutationResult, connector::MutationError> {
+    let objects = match super::argument(arguments, "objects")? {
+        Value::Array(objects) => objects,
+        _ => {
+            return Err(connector::MutationError::InvalidRequest(
+                "The objects argument must be an array.".to_string(),
+            ))
+        }
+    };
+
+    let path = path(configuration, collection, arguments)?;
+    let client = reqwest::Client::new();
+    let root = configuration.api_endpoint.to_string();
+
+    match configuration.schema.version {
+        ndc::Version::V401 => {
+            let delta = json!({ "@odata.context": "#$delta", "value": objects });
+
+            let response = client
+                .patch(format!("{root}/{}", super::encode_path(&path)))
+                .json(&delta)
+                .send()
+                .await
+                .map_err(Box::from)
+                .map_err(connector::MutationError::Other)?;
+
+            super::check(response).await?;
+        }
+
+        ndc::Version::V40 => {
+            let mut batch = super::Batch::default();
+
+            for object in objects {
+                batch.push(Method::POST, path.clone(), Some(object.clone()));
+            }
+
+            batch.send(&client, &root).await?;
+        }
+    }
+
+    Ok(super::MutationResult {
+        affected_rows: objects.len() as u32,
+    })
+}
+
+/// Apply the `set` argument to every entity matching the `predicate` argument. In 4.01, we can
+/// address the matching entities directly with `/Set/$filter(...)/$each`, as long as the service
+/// lets us count them. We ask the service to return the updated entities, and count those. A
+/// service that won't return them leaves us with the count we took beforehand, which might miss
+/// changes made in between.
+pub async fn update_many(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<super::MutationResult, connector::MutationError> {
+    let path = path(configuration, collection, arguments)?;
+    let filter = predicate(configuration, collection, arguments)?;
+    let set = super::argument(arguments, "set")?;
+
+    if !set.is_object() {
+        return Err(connector::MutationError::InvalidRequest(
+            "The set argument must be an object.".to_string(),
+        ));
+    }
+
+    let client = reqwest::Client::new();
+    let root = configuration.api_endpoint.to_string();
+
+    match configuration.schema.version {
+        // We need the number of matching entities, which we can't get if the service won't count.
+        ndc::Version::V401 if countable(configuration, collection) => {
+            let counted = count(&client, &root, &path, &filter).await?;
+
+            let response = client
+                .patch(format!(
+                    "{root}/{}/$filter({})/$each",
+                    super::encode_path(&path),
+                    super::encode_path(&filter)
+                ))
+                .header("Prefer", "return=representation")
+                .json(set)
+                .send()
+                .await
+                .map_err(Box::from)
+                .map_err(connector::MutationError::Other)?;
+
+            let body = super::check(response).await?;
+
+            let updated = serde_json::from_str::<Value>(&body)
+                .ok()
+                .and_then(|body| Some(body.get("value")?.as_array()?.len() as u32));
+
+            Ok(super::MutationResult {
+                affected_rows: updated.unwrap_or(counted),
+            })
+        }
+
+        _ => {
+            let keys = matching_keys(configuration, &client, collection, &path, &filter).await?;
+            let mut batch = super::Batch::default();
+
+            for key in &keys {
+                batch.push(Method::PATCH, format!("{path}{key}"), Some(set.clone()));
+            }
+
+            batch.send(&client, &root).await?;
+
+            Ok(super::MutationResult {
+                affected_rows: keys.len() as u32,
+            })
+        }
+    }
+}
+
+/// Delete every entity matching the `predicate` argument. In 4.01, we can address the matching
+/// entities directly with `/Set/$filter(...)/$each`, as long as the service lets us count them.
+/// The service doesn't tell us what it deleted, so the number of affected rows is the count we
+/// take beforehand, and it's only approximate: it misses changes made in between.
+pub async fn delete_many(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<super::MutationResult, connector::MutationError> {
+    let path = path(configuration, collection, arguments)?;
+    let filter = predicate(configuration, collection, arguments)?;
+
+    let client = reqwest::Client::new();
+    let root = configuration.api_endpoint.to_string();
+
+    match configuration.schema.version {
+        // We need the number of matching entities, which we can't get if the service won't count.
+        ndc::Version::V401 if countable(configuration, collection) => {
+            let affected_rows = count(&client, &root, &path, &filter).await?;
+
+            let response = client
+                .delete(format!(
+                    "{root}/{}/$filter({})/$each",
+                    super::encode_path(&path),
+                    super::encode_path(&filter)
+                ))
+                .send()
+                .await
+                .map_err(Box::from)
+                .map_err(connector::MutationError::Other)?;
+
+            super::check(response).await?;
+            Ok(super::MutationResult { affected_rows })
+        }
+
+        _ => {
+            let keys = matching_keys(configuration, &client, collection, &path, &filter).await?;
+            let mut batch = super::Batch::default();
+
+            for key in &keys {
+                batch.push(Method::DELETE, format!("{path}{key}"), None);
+            }
+
+            batch.send(&client, &root).await?;
+
+            Ok(super::MutationResult {
+                affected_rows: keys.len() as u32,
+            })
+        }
+    }
+}
+
+/// The resource path of the collection's entities, which, for a contained collection, depends on
+/// the arguments.
+fn path(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<String, connector::MutationError> {
+    query::collection_path(configuration, collection, arguments)
+        .map_err(connector::MutationError::InvalidRequest)
+}
+
+/// Translate the `predicate` argument into an OData filter, using the same machinery that we use
+/// for the predicates in queries.
+fn predicate(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<String, connector::MutationError> {
+    let expression: models::Expression =
+        serde_json::from_value(super::argument(arguments, "predicate")?.clone())
+            .map_err(|error| connector::MutationError::InvalidRequest(error.to_string()))?;
+
+    if let Some(restrictions) = configuration
+        .schema
+        .collection(collection)
+        .map(|collection| &collection.restrictions)
+    {
+        query::restrictions::check_predicate(restrictions, collection, &expression)
+            .map_err(connector::MutationError::UnsupportedOperation)?;
+    }
+
+    query::validation::check_predicate(configuration, collection, &expression)
+        .map_err(connector::MutationError::InvalidRequest)?;
+
+    let relationships = BTreeMap::new();
+    let scope = query::Scope::new(configuration, &relationships, collection);
+
+    let filter = query::Filter::from_predicate(&expression, &scope)
+        .map_err(connector::MutationError::InvalidRequest)?;
+
+    Ok(filter.to_odata_filter())
+}
+
+/// Can we count the entities of the collection with `/$count`?
+fn countable(configuration: &ndc::Configuration, collection: &str) -> bool {
+    match configuration.schema.collection(collection) {
+        Some(collection) => collection.restrictions.countable,
+        None => true,
+    }
+}
+
+/// Count the entities in a collection that match the given filter.
+async fn count(
+    client: &reqwest::Client,
+    root: &str,
+    path: &str,
+    filter: &str,
+) -> Result<u32, connector::MutationError> {
+    let response = client
+        .get(format!("{root}/{}/$count", super::encode_path(path)))
+        .query(&[("$filter", filter)])
+        .send()
+        .await
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)?;
+
+    super::check(response)
+        .await?
+        .trim()
+        .parse()
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)
+}
+
+/// Find the key predicates of every entity in a collection that matches the given filter, so that
+/// we can address each of them individually. If the service pages its results, we follow its next
+/// links until we've seen every page: otherwise, we'd only touch the entities on the first.
+async fn matching_keys(
+    configuration: &ndc::Configuration,
+    client: &reqwest::Client,
+    collection: &str,
+    path: &str,
+    filter: &str,
+) -> Result<Vec<String>, connector::MutationError> {
+    let key = &configuration
+        .schema
+        .collection(collection)
+        .ok_or_else(|| {
+            connector::MutationError::InvalidRequest(format!("Unknown collection: {collection}"))
+        })?
+        .key;
+
+    // Parts of the key may be nested within complex properties, which we select as a whole.
+    let mut select: Vec<&str> = key
+        .iter()
+        .filter_map(|part| part.path().split('/').next())
+        .collect();
+
+    select.dedup();
+
+    let root = configuration.api_endpoint.to_string();
+    let mut request = client
+        .get(format!("{root}/{}", super::encode_path(path)))
+        .query(&[("$filter", filter), ("$select", &select.join(","))]);
+
+    let mut keys = Vec::new();
+
+    loop {
+        let response = request
+            .send()
+            .await
+            .map_err(Box::from)
+            .map_err(connector::MutationError::Other)?;
+
+        let body: query::Response = serde_json::from_str(&super::check(response).await?)
+            .map_err(Box::from)
+            .map_err(connector::MutationError::Other)?;
+
+        for row in &body.value {
+            let key = query::literals::key_predicate_from_row(configuration, collection, row)
+                .map_err(Box::from)
+                .map_err(connector::MutationError::Other)?;
+
+            keys.push(key);
+        }
+
+        match body.next_page(&root) {
+            Some(next_page) => request = client.get(next_page),
+            None => return Ok(keys),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! The procedures that we generate for managing the relationships between entities. OData exposes
+//! the link between two entities as a resource in its own right, addressed by appending `$ref` to
+//! the navigation property, and we create and remove links by writing to that resource.
+
+use crate::query::literals;
+use metadata::ndc;
+use ndc_sdk::connector;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+/// Add an entity to a collection-valued navigation property: `POST /People('a')/Friends/$ref`.
+pub async fn link(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    navigation: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<super::MutationResult, connector::MutationError> {
+    let reference = Reference::from_arguments(configuration, collection, navigation, arguments)?;
+    let target_key = super::argument(arguments, "target_key")?;
+
+    let response = reqwest::Client::new()
+        .post(reference.url())
+        .json(&json!({ "@odata.id": reference.target_id(configuration, target_key)? }))
+        .send()
+        .await
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)?;
+
+    super::check(response).await?;
+    Ok(super::MutationResult { affected_rows: 1 })
+}
+
+/// Remove an entity from a collection-valued navigation property:
+/// `DELETE /People('a')/Friends/$ref?$id=People('b')`.
+pub async fn unlink(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    navigation: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<super::MutationResult, connector::MutationError> {
+    let reference = Reference::from_arguments(configuration, collection, navigation, arguments)?;
+    let target_key = super::argument(arguments, "target_key")?;
+
+    let response = reqwest::Client::new()
+        .delete(reference.url())
+        .query(&[("$id", reference.target_id(configuration, target_key)?)])
+        .send()
+        .await
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)?;
+
+    super::check(response).await?;
+    Ok(super::MutationResult { affected_rows: 1 })
+}
+
+/// Point a single-valued navigation property at an entity with `PUT /People('a')/BestFriend/$ref`,
+/// or clear it with `DELETE` if we don't have a target.
+pub async fn set_link(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    navigation: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<super::MutationResult, connector::MutationError> {
+    let reference = Reference::from_arguments(configuration, collection, navigation, arguments)?;
+    let client = reqwest::Client::new();
+
+    let request = match arguments.get("target_key") {
+        None | Some(Value::Null) => client.delete(reference.url()),
+        Some(target_key) => client
+            .put(reference.url())
+            .json(&json!({ "@odata.id": reference.target_id(configuration, target_key)? })),
+    };
+
+    let response = request
+        .send()
+        .await
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)?;
+
+    super::check(response).await?;
+    Ok(super::MutationResult { affected_rows: 1 })
+}
+
+/// Everything we need to address the link resource for a given navigation property.
+struct Reference {
+    root: String,
+    source: String,
+    navigation: String,
+    target_collection: String,
+}
+
+impl Reference {
+    fn from_arguments(
+        configuration: &ndc::Configuration,
+        collection: &str,
+        navigation: &str,
+        arguments: &BTreeMap<String, Value>,
+    ) -> Result<Self, connector::MutationError> {
+        let target_collection = configuration
+            .schema
+            .collection(collection)
+            .and_then(|collection| collection.relationships.get(navigation))
+            .ok_or_else(|| {
+                connector::MutationError::InvalidRequest(format!(
+                    "Unknown relationship {navigation} on {collection}"
+                ))
+            })?;
+
+        let key = super::argument(arguments, "key")?;
+        let key_predicate = literals::key_predicate(configuration, collection, key)
+            .map_err(connector::MutationError::InvalidRequest)?;
+
+        Ok(Reference {
+            root: configuration.api_endpoint.to_string(),
+            source: format!("{collection}{key_predicate}"),
+            navigation: navigation.to_string(),
+            target_collection: target_collection.clone(),
+        })
+    }
+
+    /// The URL of the link resource: `/People('a')/Friends/$ref`.
+    fn url(&self) -> String {
+        let path = format!("{}/{}", self.source, self.navigation);
+        format!("{}/{}/$ref", self.root, super::encode_path(&path))
+    }
+
+    /// The entity ID of the target entity, which is how OData identifies the other end of a link.
+    /// The ID is the entity's canonical URL, so it names the entity set in which the entity lives
+    /// rather than the collection that we expose: the target of `People_Employee` lives in `People`.
+    fn target_id(
+        &self,
+        configuration: &ndc::Configuration,
+        target_key: &Value,
+    ) -> Result<String, connector::MutationError> {
+        let entity_set = entity_set(configuration, &self.target_collection)?;
+
+        let key_predicate = literals::key_predicate(configuration, entity_set, target_key)
+            .map_err(connector::MutationError::InvalidRequest)?;
+
+        let path = format!("{entity_set}{key_predicate}");
+        Ok(format!("{}/{}", self.root, super::encode_path(&path)))
+    }
+}
+
+/// Find the entity set that holds the entities of a collection. Derived collections are type casts
+/// of an entity set, but contained entities (and function results) have no entity set, so there's
+/// no way to refer to them by key alone.
+fn entity_set<'a>(
+    configuration: &'a ndc::Configuration,
+    collection: &'a str,
+) -> Result<&'a str, connector::MutationError> {
+    let details = configuration.schema.collection(collection).ok_or_else(|| {
+        connector::MutationError::InvalidRequest(format!("Unknown collection: {collection}"))
+    })?;
+
+    match (&details.type_cast, &details.containment, &details.function) {
+        (Some(type_cast), _, _) => entity_set(configuration, &type_cast.collection),
+        (None, None, None) => Ok(collection),
+        _ => Err(connector::MutationError::InvalidRequest(format!(
+            "Entities of {collection} don't live in an entity set, so we can't link to them by key alone."
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::request::tests::configuration;
+
+    #[test]
+    fn test_target_id_of_derived_collection() {
+        let configuration = configuration();
+
+        let reference = Reference {
+            root: "http://localhost".to_string(),
+            source: "People('a')".to_string(),
+            navigation: "Friends".to_string(),
+            target_collection: "People_Employee".to_string(),
+        };
+
+        let target_id = reference.target_id(&configuration, &json!("b"));
+        assert_eq!(
+            target_id.ok(),
+            Some("http://localhost/People('b')".to_string())
+        );
+    }
+}
@@ -0,0 +1,35 @@
+//! The procedures that we generate for writing to singletons. A singleton always exists, so the
+//! only thing we can do is update it.
+
+use metadata::ndc;
+use ndc_sdk::connector;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Apply the `set` argument to a singleton: `PATCH /Me`.
+pub async fn update(
+    configuration: &ndc::Configuration,
+    singleton: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<super::MutationResult, connector::MutationError> {
+    let set = super::argument(arguments, "set")?;
+
+    if !set.is_object() {
+        return Err(connector::MutationError::InvalidRequest(
+            "The set argument must be an object.".to_string(),
+        ));
+    }
+
+    let root = configuration.api_endpoint.to_string();
+
+    let response = reqwest::Client::new()
+        .patch(format!("{root}/{}", super::encode_path(singleton)))
+        .json(set)
+        .send()
+        .await
+        .map_err(Box::from)
+        .map_err(connector::MutationError::Other)?;
+
+    super::check(response).await?;
+    Ok(super::MutationResult { affected_rows: 1 })
+}
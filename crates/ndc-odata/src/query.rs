@@ -1,19 +1,29 @@
 //! The handler for the `/query` endpoint.
 
+pub mod containment;
 pub mod fields;
 pub mod filters;
+pub mod functions;
+pub mod geography;
+pub mod literals;
 pub mod order_by;
 #[allow(clippy::module_inception)]
 pub mod query; // We can remove module inception when we fully move to use NDC requests.
 pub mod request;
 pub mod response;
+pub mod restrictions;
+pub mod scope;
+pub mod validation;
 
+pub use containment::*;
 pub use fields::*;
 pub use filters::*;
+pub use functions::*;
 pub use order_by::*;
 pub use query::*;
 pub use request::*;
 pub use response::*;
+pub use scope::*;
 
 use indexmap::IndexMap;
 use metadata::ndc;
@@ -23,16 +33,31 @@ pub async fn execute_query(
     configuration: &ndc::Configuration,
     request: models::QueryRequest,
 ) -> Result<models::QueryResponse, connector::QueryError> {
+    restrictions::check(
+        configuration,
+        &request.collection_relationships,
+        &request.collection,
+        &request.query,
+    )
+    .map_err(connector::QueryError::UnsupportedOperation)?;
+
+    validation::check(
+        configuration,
+        &request.collection_relationships,
+        &request.collection,
+        &request.query,
+    )
+    .map_err(connector::QueryError::InvalidRequest)?;
+
     let request_structure = Request::from_user_request(configuration, &request)
-        .map_err(Box::from)
-        .map_err(connector::QueryError::Other)?;
+        .map_err(connector::QueryError::InvalidRequest)?;
 
     let request_url = request_structure
         .to_url()
         .map_err(Box::from)
         .map_err(connector::QueryError::Other)?;
 
-    let body: Response = reqwest::get(request_url)
+    let body: serde_json::Map<String, serde_json::Value> = reqwest::get(request_url)
         .await
         .map_err(Box::from)
         .map_err(connector::QueryError::Other)?
@@ -41,12 +66,51 @@ pub async fn execute_query(
         .map_err(Box::from)
         .map_err(connector::QueryError::Other)?;
 
+    let result_rows = match request_structure.shape {
+        // We follow the service's next links until we have every page of results.
+        Shape::Rows => {
+            let mut body: Response = serde_json::from_value(serde_json::Value::Object(body))
+                .map_err(Box::from)
+                .map_err(connector::QueryError::Other)?;
+
+            let root = configuration.api_endpoint.to_string();
+            let mut value = std::mem::take(&mut body.value);
+
+            while let Some(next_page) = body.next_page(&root) {
+                body = reqwest::get(next_page)
+                    .await
+                    .map_err(Box::from)
+                    .map_err(connector::QueryError::Other)?
+                    .json()
+                    .await
+                    .map_err(Box::from)
+                    .map_err(connector::QueryError::Other)?;
+
+                value.append(&mut body.value);
+            }
+
+            value
+                .iter()
+                .map(|result_row| Response::interpret(result_row, &request_structure.query))
+                .collect()
+        }
+
+        Shape::Entity => match functions::unwrap_result(body, true) {
+            serde_json::Value::Object(result) => {
+                Vec::from([Response::interpret(&result, &request_structure.query)])
+            }
+            _ => Vec::new(),
+        },
+
+        Shape::Value { object } => functions::interpret(body, object, &request_structure.query),
+    };
+
     let mut rows = Vec::new();
 
-    for result_row in &body.value {
+    for result_row in result_rows {
         let mut row = IndexMap::new();
 
-        for (field, value) in Response::interpret(result_row, &request_structure.query) {
+        for (field, value) in result_row {
             row.insert(field.clone(), models::RowFieldValue(value));
         }
 
@@ -0,0 +1,46 @@
+//! Contained entities have no entity set of their own, so we can only reach them through an entity
+//! of their parent collection. Similarly, we reach the entities of a derived type through a type
+//! cast on their entity set.
+
+use metadata::ndc;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// The resource path at which we find a collection's entities. For an entity set, this is just its
+/// name, but a contained collection lives beneath an entity of its parent collection, which we
+/// address using the key in the collection's arguments: `People('x')/Trips`. A collection of a
+/// derived type lives at `People/Trippin.Employee`.
+pub fn collection_path(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<String, String> {
+    let containment = match configuration.schema.collection(collection) {
+        Some(ndc::Collection {
+            containment: Some(containment),
+            ..
+        }) => containment,
+
+        // The entities of a derived type are found by casting the base collection.
+        Some(ndc::Collection {
+            type_cast: Some(type_cast),
+            ..
+        }) => {
+            let base = collection_path(configuration, &type_cast.collection, arguments)?;
+            return Ok(format!("{base}/{}", type_cast.entity_type));
+        }
+
+        _ => return Ok(collection.to_string()),
+    };
+
+    let parent = collection_path(configuration, &containment.parent, arguments)?;
+    let key = arguments
+        .get(&containment.key_argument)
+        .ok_or_else(|| format!("Missing argument: {}", containment.key_argument))?;
+
+    Ok(format!(
+        "{parent}{}/{}",
+        super::literals::key_predicate(configuration, &containment.parent, key)?,
+        containment.navigation
+    ))
+}
@@ -20,7 +20,7 @@ pub struct Relationship {
 }
 
 impl Fields {
-    pub fn from_user_query(query: &models::Query) -> Result<Self, String> {
+    pub fn from_user_query(query: &models::Query, scope: &super::Scope) -> Result<Self, String> {
         let mut columns = BTreeMap::new();
         let mut relationships = BTreeMap::new();
 
@@ -36,7 +36,8 @@ impl Fields {
                         relationship,
                         arguments: _,
                     } => {
-                        let query = super::Query::from_user_query(query)?;
+                        let query =
+                            super::Query::from_user_query(query, &scope.through(relationship))?;
                         let relationship = relationship.clone();
 
                         relationships.insert(
@@ -1,4 +1,5 @@
 use itertools::Itertools;
+use metadata::ndc::derived_types::DISCRIMINATOR;
 use ndc_sdk::models;
 
 #[derive(Eq, PartialEq)]
@@ -18,33 +19,54 @@ pub enum Filter {
     IsNull {
         column: String,
     },
-    Equals {
+
+    /// A binary comparison, such as `eq`, or `has` for flag enums. The left-hand side is usually
+    /// a column, but it can also be a function of one, such as `geo.length(Route)`.
+    Compare {
         column: String,
+        operator: String,
         comparison_value: ComparisonValue,
     },
+
+    /// Is the entity of the given derived type (or a type derived from that)?
+    IsOf {
+        r#type: String,
+    },
 }
 
 #[derive(Eq, PartialEq)]
 pub enum ComparisonValue {
-    Column { column: String },
-    Scalar { value: serde_json::Value },
+    Column {
+        column: String,
+    },
+
+    /// A scalar value, already written as an OData literal of the column's type.
+    Literal {
+        literal: String,
+    },
 }
 
 impl Filter {
-    pub fn from_user_query(query: &models::Query) -> Result<Option<Self>, String> {
+    pub fn from_user_query(
+        query: &models::Query,
+        scope: &super::Scope,
+    ) -> Result<Option<Self>, String> {
         match &query.predicate {
-            Some(predicate) => Self::from_predicate(predicate).map(Some),
+            Some(predicate) => Self::from_predicate(predicate, scope).map(Some),
             None => Ok(None),
         }
     }
 
-    fn from_predicate(expression: &models::Expression) -> Result<Self, String> {
+    pub fn from_predicate(
+        expression: &models::Expression,
+        scope: &super::Scope,
+    ) -> Result<Self, String> {
         match expression {
             models::Expression::And { expressions } => {
                 let mut prepared = Vec::new();
 
                 for predicate in expressions {
-                    prepared.push(Self::from_predicate(predicate)?);
+                    prepared.push(Self::from_predicate(predicate, scope)?);
                 }
 
                 Ok(Filter::And {
@@ -56,7 +78,7 @@ impl Filter {
                 let mut prepared = Vec::new();
 
                 for predicate in expressions {
-                    prepared.push(Self::from_predicate(predicate)?);
+                    prepared.push(Self::from_predicate(predicate, scope)?);
                 }
 
                 Ok(Filter::Or {
@@ -65,7 +87,7 @@ impl Filter {
             }
 
             models::Expression::Not { expression } => Ok(Filter::Not {
-                expression: Box::new(Self::from_predicate(expression)?),
+                expression: Box::new(Self::from_predicate(expression, scope)?),
             }),
 
             models::Expression::UnaryComparisonOperator {
@@ -73,7 +95,7 @@ impl Filter {
                 operator: _,
             } => match column {
                 models::ComparisonTarget::Column { name, path: _ } => Ok(Filter::IsNull {
-                    column: name.clone(),
+                    column: Self::cast(name, scope)?,
                 }),
                 models::ComparisonTarget::RootCollectionColumn { name: _ } => {
                     Err("Root comparisons are not yet implemented.".to_string())
@@ -96,10 +118,41 @@ impl Filter {
                 operator,
                 value,
             } => {
-                if operator != &models::BinaryComparisonOperator::Equal {
-                    return Err("Non-equality filtering is not yet implemented.".to_string());
+                let column = match column {
+                    models::ComparisonTarget::Column { name, path } => {
+                        if !path.is_empty() {
+                            return Err("Column paths not yet supported.".to_string());
+                        }
+
+                        Self::cast(name, scope)?
+                    }
+
+                    models::ComparisonTarget::RootCollectionColumn { name: _ } => {
+                        return Err("Root comparisons are not yet implemented.".to_string())
+                    }
+                };
+
+                // The discriminator isn't a property, so we can only test it with `isof`.
+                if column == DISCRIMINATOR {
+                    return Self::is_of(operator, value, scope);
                 }
 
+                let operator = match operator {
+                    models::BinaryComparisonOperator::Equal => "eq".to_string(),
+                    models::BinaryComparisonOperator::Other { name } if name == "has" => {
+                        match scope.enum_type(&column) {
+                            Some((_, enum_type)) if enum_type.is_flags => "has".to_string(),
+                            _ => return Err(format!("Only flag enums support has, not {column}.")),
+                        }
+                    }
+                    models::BinaryComparisonOperator::Other { name } => {
+                        return match super::geography::filter(scope, &column, name, value)? {
+                            Some(filter) => Ok(filter),
+                            None => Err(format!("Unsupported comparison operator: {name}")),
+                        }
+                    }
+                };
+
                 let comparison_value = match value {
                     models::ComparisonValue::Column { column } => match column {
                         models::ComparisonTarget::Column { name, path } => {
@@ -108,7 +161,7 @@ impl Filter {
                             }
 
                             ComparisonValue::Column {
-                                column: name.clone(),
+                                column: Self::cast(name, scope)?,
                             }
                         }
 
@@ -118,36 +171,83 @@ impl Filter {
                             );
                         }
                     },
-                    models::ComparisonValue::Scalar { value } => ComparisonValue::Scalar {
-                        value: value.clone(),
+                    models::ComparisonValue::Scalar { value } => ComparisonValue::Literal {
+                        literal: scope.literal(&column, value)?,
                     },
                     models::ComparisonValue::Variable { name: _ } => {
                         return Err("Filtering with variables is not yet implemented.".to_string())
                     }
                 };
 
-                let column = match column {
-                    models::ComparisonTarget::Column { name, path } => {
-                        if !path.is_empty() {
-                            return Err("Column paths not yet supported.".to_string());
-                        }
-
-                        name.clone()
-                    }
-
-                    models::ComparisonTarget::RootCollectionColumn { name: _ } => {
-                        return Err("Root comparisons are not yet implemented.".to_string())
-                    }
-                };
-
-                Ok(Filter::Equals {
+                Ok(Filter::Compare {
                     column,
+                    operator,
                     comparison_value,
                 })
             }
         }
     }
 
+    fn is_of(
+        operator: &models::BinaryComparisonOperator,
+        value: &models::ComparisonValue,
+        scope: &super::Scope,
+    ) -> Result<Self, String> {
+        match (operator, value) {
+            (
+                models::BinaryComparisonOperator::Other { name },
+                models::ComparisonValue::Scalar {
+                    value: serde_json::Value::String(type_name),
+                },
+            ) if name == "isof" => {
+                Self::check_type(type_name, scope)?;
+
+                Ok(Filter::IsOf {
+                    r#type: type_name.clone(),
+                })
+            }
+
+            _ => Err(format!(
+                "{DISCRIMINATOR} only supports isof with the name of an entity type."
+            )),
+        }
+    }
+
+    /// A column can be a property of a derived type, reached through a type cast, such as
+    /// `Trippin.Employee/Cost`. This is how we `cast` the rows in a filter: rows of other types
+    /// don't have the property, so any comparison with it is false for them.
+    fn cast(column: &str, scope: &super::Scope) -> Result<String, String> {
+        if let Some((type_name, property)) = column.rsplit_once('/') {
+            Self::check_type(type_name, scope)?;
+
+            if scope.field(column).is_none() {
+                return Err(format!("{type_name} has no property {property}."));
+            }
+        }
+
+        Ok(column.to_string())
+    }
+
+    /// Check that we can test for, or cast to, the given entity type.
+    fn check_type(type_name: &str, scope: &super::Scope) -> Result<(), String> {
+        if !scope
+            .configuration
+            .schema
+            .object_types
+            .contains_key(type_name)
+        {
+            return Err(format!("Unknown entity type: {type_name}"));
+        }
+
+        // If we know the type of the rows, the type we test for has to be in its hierarchy:
+        // `isof` or `cast` with any other type would never match.
+        if scope.object_type.is_some() && !scope.hierarchy().contains(type_name) {
+            return Err(format!("These rows can never be of type {type_name}."));
+        }
+
+        Ok(())
+    }
+
     pub fn to_odata_filter(&self) -> String {
         match self {
             Filter::And { expressions } => {
@@ -176,17 +276,17 @@ impl Filter {
                 format!("({} eq null)", column)
             }
 
-            Filter::Equals {
+            Filter::IsOf { r#type } => format!("isof({})", r#type),
+
+            Filter::Compare {
                 column,
+                operator,
                 comparison_value,
             } => match comparison_value {
-                ComparisonValue::Column { column } => format!("({} eq {})", column, column.clone()),
-                ComparisonValue::Scalar {
-                    value: serde_json::Value::String(s),
-                } => format!("({} eq '{}')", column, s.clone()),
-                ComparisonValue::Scalar { value } => {
-                    format!("({} eq {})", column, value)
+                ComparisonValue::Column { column: other } => {
+                    format!("({column} {operator} {other})")
                 }
+                ComparisonValue::Literal { literal } => format!("({column} {operator} {literal})"),
             },
         }
     }
@@ -0,0 +1,252 @@
+//! Invoking OData functions as NDC functions. NDC functions are queried like collections, but
+//! their arguments are passed in the request, and their results are returned in a single row under
+//! the `__value` field. Functions that return objects can also be queried for the fields of those
+//! objects, which we push down to the service with `$select` and `$expand`.
+
+use metadata::ndc;
+use ndc_sdk::models;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// The field under which NDC expects to find the result of a function.
+pub const VALUE_FIELD: &str = "__value";
+
+/// Render a function invocation as a resource path, such as `GetNearestAirport(lat=1.0,lon=2.0)`.
+/// Each argument is written as a literal of its declared type. Only nullable parameters can be left
+/// out, in which case we pass `null`.
+pub fn invocation(
+    function: &str,
+    parameters: &BTreeMap<String, ndc::Type>,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<String, String> {
+    let mut rendered = Vec::new();
+
+    for (name, argument_type) in parameters {
+        let value = match arguments.get(name) {
+            Some(value) => value,
+            None if matches!(argument_type, ndc::Type::Nullable { .. }) => &Value::Null,
+            None => return Err(format!("Missing argument: {name}")),
+        };
+
+        let r#type = argument_type.underlying_type().to_string();
+
+        let literal = super::literals::encode_as(value, &r#type)
+            .map_err(|reason| format!("Invalid value for {name}: {reason}"))?;
+
+        rendered.push(format!("{name}={literal}"));
+    }
+
+    Ok(format!("{function}({})", rendered.join(",")))
+}
+
+/// The full path at which we invoke a function. Unbound functions live at the service root, but
+/// bound functions are invoked on the entities to which they're bound.
+pub fn path(
+    configuration: &ndc::Configuration,
+    function: &ndc::Function,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<String, String> {
+    if let Some(singleton) = &function.singleton {
+        return Ok(singleton.clone());
+    }
+
+    match &function.binding {
+        // Older configurations don't record the import, but they only ever used the function's
+        // own name.
+        None => invocation(
+            function.import.as_ref().unwrap_or(&function.name),
+            &function.arguments,
+            arguments,
+        ),
+
+        Some(binding) => {
+            let mut parameters = function.arguments.clone();
+            parameters.remove(&binding.parameter);
+
+            Ok(format!(
+                "{}/{}",
+                binding_path(configuration, binding, arguments)?,
+                invocation(&binding.operation, &parameters, arguments)?
+            ))
+        }
+    }
+}
+
+/// The path to the entity or entities to which a bound operation is bound: `People('a')` for an
+/// entity, or `People` for a collection. Collections can be narrowed with a predicate, but only
+/// OData 4.01 gives us the `$filter` path segment we need to do so.
+pub fn binding_path(
+    configuration: &ndc::Configuration,
+    binding: &ndc::Binding,
+    arguments: &BTreeMap<String, Value>,
+) -> Result<String, String> {
+    let argument = arguments.get(&binding.parameter).unwrap_or(&Value::Null);
+
+    if !binding.is_collection {
+        if argument.is_null() {
+            return Err(format!("Missing argument: {}", binding.parameter));
+        }
+
+        return Ok(format!(
+            "{}{}",
+            binding.collection,
+            super::literals::key_predicate(configuration, &binding.collection, argument)?
+        ));
+    }
+
+    if argument.is_null() {
+        return Ok(binding.collection.clone());
+    }
+
+    if configuration.schema.version == ndc::Version::V40 {
+        return Err("Filtering a bound collection requires OData 4.01.".to_string());
+    }
+
+    let expression: models::Expression =
+        serde_json::from_value(argument.clone()).map_err(|error| error.to_string())?;
+
+    let relationships = BTreeMap::new();
+    let scope = super::Scope::new(configuration, &relationships, &binding.collection);
+
+    Ok(format!(
+        "{}/$filter({})",
+        binding.collection,
+        super::Filter::from_predicate(&expression, &scope)?.to_odata_filter()
+    ))
+}
+
+/// Singletons know, from their navigation property bindings, which collection each of their
+/// navigation properties leads to. A relationship that the query follows from a singleton has to
+/// lead to the same collection, or we would read the related rows as if they were something else.
+pub fn check_relationships(
+    function: &ndc::Function,
+    request: &models::QueryRequest,
+) -> Result<(), String> {
+    let relationships = request
+        .query
+        .fields
+        .iter()
+        .flat_map(|fields| fields.values())
+        .filter_map(|field| match field {
+            models::Field::Relationship { relationship, .. } => Some(relationship),
+            models::Field::Column { .. } => None,
+        });
+
+    for relationship in relationships {
+        let bound = function.relationships.get(relationship);
+        let requested = request
+            .collection_relationships
+            .get(relationship)
+            .map(|relationship| &relationship.target_collection);
+
+        if let (Some(bound), Some(requested)) = (bound, requested) {
+            if bound != requested {
+                return Err(format!(
+                    "{relationship} leads from {} to {bound}, not {requested}.",
+                    function.name
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Functions can only be invoked with literal arguments: we have no way to run a function once per
+/// set of variables.
+pub fn literal_arguments(
+    arguments: &BTreeMap<String, models::Argument>,
+) -> Result<BTreeMap<String, Value>, String> {
+    let mut literals = BTreeMap::new();
+
+    for (name, argument) in arguments {
+        match argument {
+            models::Argument::Literal { value } => literals.insert(name.clone(), value.clone()),
+            models::Argument::Variable { name: _ } => {
+                return Err("Function arguments from variables are not yet supported.".to_string())
+            }
+        };
+    }
+
+    Ok(literals)
+}
+
+/// Does the given function return a single object, rather than a scalar or a collection?
+pub fn returns_object(configuration: &ndc::Configuration, result_type: &ndc::Type) -> bool {
+    !result_type.is_collection()
+        && configuration
+            .schema
+            .object_types
+            .contains_key(&result_type.underlying_type().to_string())
+}
+
+/// How we read a function's result. A query that only asks for `__value` gets the whole result.
+/// Otherwise, the query's fields select within the object (or objects) that the function returns,
+/// just as they would within the rows of a collection.
+pub fn shape(
+    configuration: &ndc::Configuration,
+    function: &ndc::Function,
+    query: &super::Query,
+) -> Result<super::Shape, String> {
+    let object = returns_object(configuration, &function.result_type);
+
+    if query.selects_value_only() {
+        return Ok(super::Shape::Value { object });
+    }
+
+    if query.selects_value() {
+        return Err(format!(
+            "{} can't select {} alongside other fields.",
+            function.name,
+            super::VALUE_FIELD
+        ));
+    }
+
+    let result_type = function.result_type.underlying_type().to_string();
+
+    if !configuration.schema.object_types.contains_key(&result_type) {
+        return Err(format!(
+            "{} returns {}, so the only field we can select is {}.",
+            function.name,
+            result_type,
+            super::VALUE_FIELD
+        ));
+    }
+
+    match function.result_type.is_collection() {
+        true => Ok(super::Shape::Rows),
+        false => Ok(super::Shape::Entity),
+    }
+}
+
+/// Take the result of a function or action out of the service's response. Objects are returned as
+/// the body itself, annotated with `@odata.context` and the like. Scalars and collections are
+/// wrapped in a `value` field.
+pub fn unwrap_result(body: Map<String, Value>, object: bool) -> Value {
+    if object {
+        Value::Object(
+            body.into_iter()
+                .filter(|(key, _)| !key.starts_with('@'))
+                .collect(),
+        )
+    } else {
+        body.get("value").cloned().unwrap_or(Value::Null)
+    }
+}
+
+/// Turn the service's response to a function invocation into rows, when the query only asks for
+/// `__value`. The whole result lives under that field of a single row.
+pub fn interpret(
+    body: Map<String, Value>,
+    object: bool,
+    query: &super::Query,
+) -> Vec<Map<String, Value>> {
+    let result = unwrap_result(body, object);
+    let mut row = Map::new();
+
+    for alias in query.fields.columns.keys() {
+        row.insert(alias.0.clone(), result.clone());
+    }
+
+    Vec::from([row])
+}
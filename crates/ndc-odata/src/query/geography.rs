@@ -0,0 +1,91 @@
+//! Filtering on spatial columns with OData's `geo.` functions. Each of these is exposed as a custom
+//! comparison operator on the spatial scalar types, which we translate into an ordinary comparison
+//! against the result of the function: `distance_lt` becomes `geo.distance(Location, ...) lt 10`.
+
+use metadata::ndc;
+use ndc_sdk::models;
+use serde_json::Value;
+
+/// Translate a custom comparison operator on a spatial column into a filter. If the column isn't
+/// spatial, or the operator isn't one of ours, there's nothing for us to do.
+pub fn filter(
+    scope: &super::Scope,
+    column: &str,
+    operator: &str,
+    value: &models::ComparisonValue,
+) -> Result<Option<super::Filter>, String> {
+    let column_type = match scope.column_type(column) {
+        Some(column_type) => column_type,
+        None => return Ok(None),
+    };
+
+    let family = match ndc::geography::family(&column_type) {
+        Some((family, _)) => family,
+        None => return Ok(None),
+    };
+
+    let argument_type = match ndc::geography::comparison_operators(&column_type).remove(operator) {
+        Some(argument_type) => argument_type.underlying_type().to_string(),
+        None => return Ok(None),
+    };
+
+    let value = match value {
+        models::ComparisonValue::Scalar { value } => value,
+        _ => {
+            return Err(format!(
+                "The {operator} operator only supports scalar values."
+            ))
+        }
+    };
+
+    // Values without a `crs` should be in the same reference system as the column.
+    let srid = scope
+        .field(column)
+        .and_then(|field| field.constraints.as_ref())
+        .and_then(|constraints| constraints.srid);
+
+    let compare = |left: String, operator: &str, literal: String| super::Filter::Compare {
+        column: left,
+        operator: operator.to_string(),
+        comparison_value: super::ComparisonValue::Literal { literal },
+    };
+
+    if operator == "intersects" {
+        let polygon = super::literals::encode_spatial(value, &argument_type, srid)?;
+
+        return Ok(Some(compare(
+            format!("geo.intersects({column},{polygon})"),
+            "eq",
+            "true".to_string(),
+        )));
+    }
+
+    if let Some(comparison) = operator.strip_prefix("distance_") {
+        let point_type = format!("Edm.{family}Point");
+        let from = super::literals::encode_spatial(field(value, "from")?, &point_type, srid)?;
+        let distance = super::literals::encode(field(value, "distance")?);
+
+        return Ok(Some(compare(
+            format!("geo.distance({column},{from})"),
+            comparison,
+            distance,
+        )));
+    }
+
+    if let Some(comparison) = operator.strip_prefix("length_") {
+        return Ok(Some(compare(
+            format!("geo.length({column})"),
+            comparison,
+            super::literals::encode(value),
+        )));
+    }
+
+    Ok(None)
+}
+
+/// Look up a required field of an operator's argument.
+fn field<'a>(value: &'a Value, name: &str) -> Result<&'a Value, String> {
+    value
+        .get(name)
+        .ok_or_else(|| format!("The argument is missing its {name} field."))
+}
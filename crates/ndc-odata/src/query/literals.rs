@@ -0,0 +1,315 @@
+//! Encoding JSON values as OData literals, for use in filters and key predicates.
+
+use metadata::ndc;
+use serde_json::{Map, Value};
+
+/// Encode a JSON value as an OData literal. Strings are wrapped in single quotes, with any single
+/// quotes inside doubled up as the OData ABNF requires. Everything else is written out exactly as
+/// it would be in JSON.
+pub fn encode(value: &Value) -> String {
+    match value {
+        Value::String(string) => format!("'{}'", string.replace('\'', "''")),
+        other => other.to_string(),
+    }
+}
+
+/// Encode a JSON value as an OData literal of the given primitive type. JSON only gives us strings
+/// for most of OData's primitive types, but OData writes many of them unquoted (`2024-01-01`) or
+/// with a type prefix (`duration'P1D'`), so we need to know the type we're aiming for. Spatial
+/// values have to be valid GeoJSON, which is the only way this can fail.
+pub fn encode_as(value: &Value, r#type: &str) -> Result<String, String> {
+    match (r#type, value) {
+        (
+            "Edm.Date" | "Edm.DateTimeOffset" | "Edm.Guid" | "Edm.TimeOfDay",
+            Value::String(string),
+        ) => Ok(string.clone()),
+        ("Edm.Duration", Value::String(string)) => Ok(format!("duration'{string}'")),
+        ("Edm.Binary", Value::String(string)) => Ok(format!("binary'{string}'")),
+        (_, Value::Object(_)) if ndc::geography::family(r#type).is_some() => {
+            encode_spatial(value, r#type, None)
+        }
+        _ => Ok(encode(value)),
+    }
+}
+
+/// Encode a JSON value as a member of an enum type: `Trippin.PersonGender'Male'`. Flag enums can
+/// combine members, which we accept either as a comma-separated string or as an array of member
+/// names. We also accept the integer value of a member, which OData allows in place of its name.
+pub fn encode_enum(name: &str, enum_type: &ndc::EnumType, value: &Value) -> Result<String, String> {
+    let members: Vec<String> = match value {
+        Value::Number(number) => return Ok(format!("{name}'{number}'")),
+        Value::String(string) => string
+            .split(',')
+            .map(|member| member.trim().to_string())
+            .collect(),
+        Value::Array(elements) if enum_type.is_flags => elements
+            .iter()
+            .map(|element| match element {
+                Value::String(member) => Ok(member.clone()),
+                other => Err(format!("{other} is not a member of {name}.")),
+            })
+            .collect::<Result<_, _>>()?,
+        other => return Err(format!("{other} is not a member of {name}.")),
+    };
+
+    if members.len() > 1 && !enum_type.is_flags {
+        return Err(format!(
+            "{name} is not a flag enum, so values can't combine members."
+        ));
+    }
+
+    for member in &members {
+        if !enum_type.members.contains(member) {
+            return Err(format!("{member} is not a member of {name}."));
+        }
+    }
+
+    Ok(format!("{name}'{}'", members.join(",")))
+}
+
+/// Encode a GeoJSON value as a spatial literal of the given type, using the well-known text that
+/// OData expects: `geography'SRID=4326;POINT(-122.1 47.6)'`. We take the SRID from the GeoJSON's
+/// `crs` if it has one, then from the `SRID` facet of the column we're comparing against, and
+/// otherwise use the OData defaults: 4326 for geography, and 0 for geometry.
+pub fn encode_spatial(value: &Value, r#type: &str, srid: Option<u32>) -> Result<String, String> {
+    let (family, _) = ndc::geography::family(r#type)
+        .ok_or_else(|| format!("{type} is not a spatial type.", type = r#type))?;
+
+    let default_srid = if family == "Geography" { 4326 } else { 0 };
+    let srid = spatial_reference(value).or(srid).unwrap_or(default_srid);
+
+    Ok(format!(
+        "{}'SRID={srid};{}'",
+        family.to_lowercase(),
+        well_known_text(value)?
+    ))
+}
+
+/// The SRID that a GeoJSON value names in its `crs`, as in `EPSG:4326`, if it names one.
+pub fn spatial_reference(value: &Value) -> Option<u32> {
+    value
+        .pointer("/crs/properties/name")
+        .and_then(Value::as_str)
+        .and_then(|name| name.rsplit(':').next())
+        .and_then(|srid| srid.parse().ok())
+}
+
+/// Write a GeoJSON geometry as well-known text, in the dialect that the OData ABNF uses.
+fn well_known_text(geometry: &Value) -> Result<String, String> {
+    let coordinates = geometry.get("coordinates").unwrap_or(&Value::Null);
+
+    match geometry.get("type").and_then(Value::as_str) {
+        Some("Point") => Ok(format!("POINT({})", position(coordinates)?)),
+        Some("LineString") => Ok(format!("LINESTRING({})", positions(coordinates)?)),
+        Some("Polygon") => Ok(format!("POLYGON({})", rings(coordinates)?)),
+        Some("MultiPoint") => Ok(format!(
+            "MULTIPOINT({})",
+            each(coordinates, |point| {
+                Ok(format!("({})", position(point)?))
+            })?
+        )),
+        Some("MultiLineString") => Ok(format!(
+            "MULTILINESTRING({})",
+            each(coordinates, |line| {
+                Ok(format!("({})", positions(line)?))
+            })?
+        )),
+        Some("MultiPolygon") => Ok(format!(
+            "MULTIPOLYGON({})",
+            each(coordinates, |polygon| {
+                Ok(format!("({})", rings(polygon)?))
+            })?
+        )),
+        Some("GeometryCollection") => Ok(format!(
+            "COLLECTION({})",
+            each(
+                geometry.get("geometries").unwrap_or(&Value::Null),
+                well_known_text
+            )?
+        )),
+        _ => Err(format!("{geometry} is not a GeoJSON geometry.")),
+    }
+}
+
+/// Write each element of a GeoJSON array, separated by commas.
+fn each(
+    elements: &Value,
+    write: impl Fn(&Value) -> Result<String, String>,
+) -> Result<String, String> {
+    let elements = elements
+        .as_array()
+        .ok_or_else(|| format!("Expected an array of coordinates, but got {elements}."))?;
+
+    Ok(elements
+        .iter()
+        .map(write)
+        .collect::<Result<Vec<_>, _>>()?
+        .join(","))
+}
+
+/// A position is written as its coordinates separated by spaces: `[1.0, 2.0]` becomes `1.0 2.0`.
+fn position(position: &Value) -> Result<String, String> {
+    let coordinates = position
+        .as_array()
+        .ok_or_else(|| format!("{position} is not a position."))?;
+
+    Ok(coordinates
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// A line string, or the ring of a polygon, is a comma-separated list of positions.
+fn positions(positions: &Value) -> Result<String, String> {
+    each(positions, position)
+}
+
+/// A polygon is a list of rings, each wrapped in parentheses.
+fn rings(rings: &Value) -> Result<String, String> {
+    each(rings, |ring| Ok(format!("({})", positions(ring)?)))
+}
+
+/// Build the key predicate that addresses a single entity within a collection: the `('a')` in
+/// `/People('a')`. Composite keys arrive as an object with a field for each part of the key, and
+/// we name each part: `/Flights(Airline='AA',Number=100)`. A single-part key can arrive either
+/// way. Either way, we need exactly the parts of the collection's key, each of which is written as
+/// a literal of its type.
+pub fn key_predicate(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    key: &Value,
+) -> Result<String, String> {
+    let parts = match configuration.schema.collection(collection) {
+        Some(collection) => collection.key.as_slice(),
+        None => return Err(format!("Unknown collection: {collection}")),
+    };
+
+    match (key, parts) {
+        (_, []) => Err(format!("{collection} has no key.")),
+
+        (Value::Object(values), _) => {
+            if let Some(name) = values
+                .keys()
+                .find(|name| !parts.iter().any(|part| &part.name == *name))
+            {
+                return Err(format!(
+                    "The key of {collection} has no part called {name}."
+                ));
+            }
+
+            let mut pairs = Vec::new();
+
+            for part in parts {
+                let value = values
+                    .get(&part.name)
+                    .ok_or_else(|| format!("The key of {collection} is missing {}.", part.name))?;
+
+                pairs.push(format!(
+                    "{}={}",
+                    part.name,
+                    key_part(configuration, part, value)?
+                ));
+            }
+
+            Ok(format!("({})", pairs.join(",")))
+        }
+
+        (_, [part]) => Ok(format!("({})", key_part(configuration, part, key)?)),
+
+        (_, _) => Err(format!(
+            "The key of {collection} has {} parts, so it must be an object with a field for each.",
+            parts.len()
+        )),
+    }
+}
+
+/// Write one part of a key as a literal of its type. Enum members need the definition of their
+/// enum type, which lives in the configuration. Older configurations don't record the types of
+/// keys, in which case we do the best we can with the JSON value alone.
+fn key_part(
+    configuration: &ndc::Configuration,
+    part: &ndc::KeyProperty,
+    value: &Value,
+) -> Result<String, String> {
+    let r#type = match &part.r#type {
+        Some(r#type) => r#type.to_string(),
+        None => return Ok(encode(value)),
+    };
+
+    match configuration.schema.enum_types.get(&r#type) {
+        Some(enum_type) => encode_enum(&r#type, enum_type, value),
+        None => encode_as(value, &r#type)
+            .map_err(|reason| format!("Invalid value for {}: {reason}", part.name)),
+    }
+}
+
+/// Build the key predicate for an entity, reading the value of each key property from a row that
+/// the service returned to us.
+pub fn key_predicate_from_row(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    row: &Map<String, Value>,
+) -> Result<String, String> {
+    let key = configuration
+        .schema
+        .collection(collection)
+        .map(|collection| collection.key.as_slice())
+        .unwrap_or_default();
+
+    let mut parts = Map::new();
+
+    for part in key {
+        let mut segments = part.path().split('/');
+        let first = segments.next().and_then(|segment| row.get(segment));
+
+        match segments.fold(first, |value, segment| value?.get(segment)) {
+            Some(value) => parts.insert(part.name.clone(), value.clone()),
+            None => {
+                return Err(format!(
+                    "The service didn't return the key property {}.",
+                    part.path()
+                ))
+            }
+        };
+    }
+
+    match parts.values().next() {
+        Some(value) if parts.len() == 1 => key_predicate(configuration, collection, value),
+        _ => key_predicate(configuration, collection, &Value::Object(parts)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::request::tests::configuration;
+    use serde_json::json;
+
+    #[test]
+    fn test_key_predicate() {
+        let key = key_predicate(&configuration(), "People", &json!("a'b"));
+        assert_eq!(key, Ok("('a''b')".to_string()));
+
+        let key = key_predicate(&configuration(), "People", &json!({ "UserName": "a" }));
+        assert_eq!(key, Ok("(UserName='a')".to_string()));
+    }
+
+    #[test]
+    fn test_key_predicate_with_extra_parts() {
+        let key = json!({ "UserName": "a", "Age": 30 });
+
+        assert_eq!(
+            key_predicate(&configuration(), "People", &key),
+            Err("The key of People has no part called Age.".to_string())
+        );
+    }
+
+    #[test]
+    fn test_key_predicate_with_missing_parts() {
+        assert_eq!(
+            key_predicate(&configuration(), "People", &json!({})),
+            Err("The key of People is missing UserName.".to_string())
+        );
+    }
+}
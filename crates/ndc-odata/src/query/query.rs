@@ -1,6 +1,8 @@
 use itertools::Itertools;
+use metadata::ndc::derived_types::DISCRIMINATOR;
+use metadata::ndc::open_types::DYNAMIC_PROPERTIES;
 use ndc_sdk::models;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[derive(Eq, PartialEq)]
 pub struct Query {
@@ -9,6 +11,13 @@ pub struct Query {
     pub order_by: Option<super::OrderBy>,
     pub limit: Option<u32>,
     pub offset: Option<u32>,
+
+    /// The object type of the rows, which is the type of any entity that OData doesn't annotate.
+    pub object_type: Option<String>,
+
+    /// If the query asks for the dynamic properties of an open type, the properties that each type
+    /// in its hierarchy declares: everything else in an entity of that type is dynamic.
+    pub declared_properties: Option<BTreeMap<String, BTreeSet<String>>>,
 }
 
 impl Query {
@@ -16,39 +25,85 @@ impl Query {
     // we query using `$select`) and fields in a related collection (which we query via `$expand`).
     // This method effectively just separates the two types of field and also strips any parts of
     // the query that we don't support yet.
-    pub fn from_user_query(query: &models::Query) -> Result<Self, String> {
-        let fields = super::Fields::from_user_query(query)
+    pub fn from_user_query(query: &models::Query, scope: &super::Scope) -> Result<Self, String> {
+        let fields = super::Fields::from_user_query(query, scope)
             .expect("Only queries with fields are currently supported");
 
-        let filters = super::Filter::from_user_query(query)?;
+        let filters = super::Filter::from_user_query(query, scope)?;
         let order_by = super::OrderBy::from_user_query(query);
 
         if query.aggregates.is_some() {
             return Err("Aggregation queries are not yet supported.".to_string());
         }
 
+        let declared_properties = fields
+            .columns
+            .values()
+            .any(|super::Original(name)| name == DYNAMIC_PROPERTIES)
+            .then(|| {
+                scope
+                    .hierarchy()
+                    .into_iter()
+                    .filter_map(|name| {
+                        let object_type = scope.configuration.schema.object_types.get(&name)?;
+                        Some((name, object_type.fields.keys().cloned().collect()))
+                    })
+                    .collect()
+            });
+
         Ok(Query {
             fields,
             filters,
             limit: query.limit,
             offset: query.offset,
             order_by,
+            object_type: scope.object_type.clone(),
+            declared_properties,
         })
     }
 
+    /// Does this query ask for the `__value` field of a function result at all?
+    pub fn selects_value(&self) -> bool {
+        self.fields
+            .columns
+            .values()
+            .any(|super::Original(name)| name == super::VALUE_FIELD)
+    }
+
+    /// Does this query only ask for the `__value` field of a function result?
+    pub fn selects_value_only(&self) -> bool {
+        self.fields.relationships.is_empty()
+            && self
+                .fields
+                .columns
+                .values()
+                .all(|super::Original(name)| name == super::VALUE_FIELD)
+    }
+
+    /// The properties to `$select`. The discriminator isn't a property: OData sends it as an
+    /// annotation whenever it matters. The dynamic properties column isn't one either.
     pub fn odata_fields(&self) -> Vec<&String> {
         self.fields
             .columns
             .values()
             .map(|super::Original(name)| name)
+            .filter(|name| name.as_str() != DISCRIMINATOR && name.as_str() != DYNAMIC_PROPERTIES)
             .collect()
     }
 
     pub fn prepare_parameters(&self) -> BTreeMap<String, String> {
         let mut parameters = BTreeMap::new();
 
-        let query_fields = &self.odata_fields().iter().join(", ");
-        parameters.insert("$select".to_string(), query_fields.clone());
+        // We can't `$select` dynamic properties that we don't know about, so if we want them, we
+        // take the whole entity. Likewise, if we only want the discriminator, an empty `$select`
+        // would ask for nothing at all.
+        let query_fields = self.odata_fields();
+        let whole_entity = self.declared_properties.is_some()
+            || (query_fields.is_empty() && !self.fields.columns.is_empty());
+
+        if !whole_entity {
+            parameters.insert("$select".to_string(), query_fields.iter().join(", "));
+        }
 
         if !self.fields.relationships.is_empty() {
             let mut expansions = Vec::new();
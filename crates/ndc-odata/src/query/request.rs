@@ -4,19 +4,74 @@ use url_builder::URLBuilder;
 
 pub struct Request {
     pub api_endpoint: ndc::Endpoint,
-    pub collection: String,
+    pub path: String,
+    pub shape: Shape,
     pub query: super::Query,
 }
 
+/// The shape of the service's response, which tells us how to turn it into rows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Shape {
+    /// A collection of entities in a `value` array, each of which becomes a row.
+    Rows,
+
+    /// A single object, such as the result of a function that returns an entity, which becomes
+    /// the only row.
+    Entity,
+
+    /// The result of a function, which we return under the `__value` field of a single row.
+    Value { object: bool },
+}
+
 impl Request {
     pub fn from_user_request(
         configuration: &ndc::Configuration,
         request: &models::QueryRequest,
     ) -> Result<Self, String> {
+        let arguments = super::functions::literal_arguments(&request.arguments)?;
+
+        let query = super::Query::from_user_query(
+            &request.query,
+            &super::Scope::new(
+                configuration,
+                &request.collection_relationships,
+                &request.collection,
+            ),
+        )?;
+
+        let (path, shape) = match configuration.schema.function(&request.collection) {
+            Some(function) => {
+                super::functions::check_relationships(function, request)?;
+
+                (
+                    super::functions::path(configuration, function, &arguments)?,
+                    super::functions::shape(configuration, function, &query)?,
+                )
+            }
+
+            // Composable functions are queried just like entity sets, but at the function's URL.
+            None => match configuration.schema.collection(&request.collection) {
+                Some(ndc::Collection {
+                    function: Some(function),
+                    arguments: parameters,
+                    ..
+                }) => (
+                    super::functions::invocation(function, parameters, &arguments)?,
+                    Shape::Rows,
+                ),
+
+                _ => (
+                    super::collection_path(configuration, &request.collection, &arguments)?,
+                    Shape::Rows,
+                ),
+            },
+        };
+
         Ok(Request {
             api_endpoint: configuration.api_endpoint.clone(),
-            collection: request.collection.clone(),
-            query: super::Query::from_user_query(&request.query)?,
+            path,
+            shape,
+            query,
         })
     }
 
@@ -27,12 +82,104 @@ impl Request {
             .set_protocol(&self.api_endpoint.protocol)
             .set_host(&self.api_endpoint.authority)
             .add_route(&self.api_endpoint.path)
-            .add_route(&self.collection);
+            .add_route(&crate::mutation::encode_path(&self.path));
 
-        for (parameter_name, parameter_value) in super::Query::prepare_parameters(&self.query) {
-            builder.add_param(&parameter_name, &parameter_value);
+        // A function's `__value` isn't a property we can `$select`, so we take the whole result.
+        if !matches!(self.shape, Shape::Value { .. }) {
+            for (parameter_name, parameter_value) in super::Query::prepare_parameters(&self.query) {
+                builder.add_param(&parameter_name, &parameter_value);
+            }
         }
 
         Ok(builder.build())
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// The configuration of the snapshot tests' connector.
+    pub fn configuration() -> ndc::Configuration {
+        let raw: ndc::RawConfiguration =
+            serde_json::from_str(include_str!("../../tests/configuration/configuration.json"))
+                .unwrap();
+
+        ndc::Configuration {
+            api_endpoint: ndc::Endpoint::parse(&raw.api_endpoint).unwrap(),
+            schema: raw.schema,
+        }
+    }
+
+    #[test]
+    fn test_reserved_characters_in_paths() {
+        let request: models::QueryRequest = serde_json::from_value(serde_json::json!({
+            "collection": "People_GetFriendsTrips",
+            "query": {
+                "fields": {
+                    "__value": { "type": "column", "column": "__value" }
+                }
+            },
+            "arguments": {
+                "person": { "type": "literal", "value": "a#b" },
+                "userName": { "type": "literal", "value": "50% & a?b" }
+            },
+            "collection_relationships": {}
+        }))
+        .unwrap();
+
+        let url = Request::from_user_request(&configuration(), &request)
+            .unwrap()
+            .to_url()
+            .unwrap();
+
+        assert!(url
+            .ends_with("/People('a%23b')/Trippin.GetFriendsTrips(userName='50%25%20%26%20a%3Fb')"));
+    }
+
+    #[test]
+    fn test_type_cast_filters() {
+        let request: models::QueryRequest = serde_json::from_value(serde_json::json!({
+            "collection": "People",
+            "query": {
+                "fields": {
+                    "user_name": { "type": "column", "column": "UserName" }
+                },
+                "where": {
+                    "type": "binary_comparison_operator",
+                    "column": { "type": "column", "name": "Trippin.Employee/Cost", "path": [] },
+                    "operator": { "type": "equal" },
+                    "value": { "type": "scalar", "value": 1000 }
+                }
+            },
+            "arguments": {},
+            "collection_relationships": {}
+        }))
+        .unwrap();
+
+        let url = Request::from_user_request(&configuration(), &request)
+            .unwrap()
+            .to_url()
+            .unwrap();
+
+        assert!(url.contains("$filter=(Trippin.Employee/Cost eq 1000)"));
+    }
+
+    #[test]
+    fn test_singleton_relationships() {
+        let goldenfile: serde_json::Value = serde_json::from_str(include_str!(
+            "../../tests/goldenfiles/query/singleton_relationships.json"
+        ))
+        .unwrap();
+
+        let request: models::QueryRequest =
+            serde_json::from_value(goldenfile["data"].clone()).unwrap();
+
+        let request = Request::from_user_request(&configuration(), &request).unwrap();
+        let url = request.to_url().unwrap();
+
+        assert_eq!(request.shape, Shape::Entity);
+        assert!(url.contains("/Me?"));
+        assert!(url.contains("$expand=Friends($select=UserName)"));
+    }
+}
@@ -1,3 +1,5 @@
+use metadata::ndc::derived_types::DISCRIMINATOR;
+use metadata::ndc::open_types::DYNAMIC_PROPERTIES;
 use serde::Deserialize;
 use serde_json::{Map, Value};
 
@@ -8,9 +10,28 @@ pub struct Response {
 
     #[serde(default)]
     pub value: Vec<Map<String, Value>>,
+
+    /// Services may page their results, in which case this points to the next page.
+    #[serde(rename = "@odata.nextLink", alias = "@nextLink", default)]
+    pub next_link: Option<String>,
 }
 
 impl Response {
+    /// The URL of the next page of results, if there is one. Next links are usually absolute, but
+    /// a relative link is relative to the service root.
+    pub fn next_page(&self, root: &str) -> Option<String> {
+        let link = self.next_link.as_ref()?;
+
+        match link.starts_with("http://") || link.starts_with("https://") {
+            true => Some(link.clone()),
+            false => Some(format!(
+                "{}/{}",
+                root.trim_end_matches('/'),
+                link.trim_start_matches('/')
+            )),
+        }
+    }
+
     pub fn interpret(
         result_row: &Map<String, Value>,
         query_structure: &super::Query,
@@ -18,18 +39,96 @@ impl Response {
         let mut prepared = Map::new();
 
         for (alias, original) in &query_structure.fields.columns {
-            if let Some(value) = result_row.get(original.0.as_str()) {
+            if original.0 == DISCRIMINATOR {
+                let entity_type = Response::entity_type(result_row, query_structure);
+
+                prepared.insert(
+                    alias.0.clone(),
+                    entity_type.map_or(Value::Null, |entity_type| {
+                        Value::String(entity_type.to_string())
+                    }),
+                );
+            } else if original.0 == DYNAMIC_PROPERTIES {
+                let dynamic_properties = Response::dynamic_properties(result_row, query_structure);
+                prepared.insert(alias.0.clone(), Value::Object(dynamic_properties));
+            } else if let Some(value) = result_row.get(original.0.as_str()) {
                 prepared.insert(alias.0.clone(), value.clone());
             }
         }
 
         for (alias, expansion) in &query_structure.fields.relationships {
-            if let Some(Value::Object(obj)) = result_row.get(&expansion.relationship) {
-                let subresponse = Response::interpret(obj, &expansion.query);
-                prepared.insert(alias.0.clone(), Value::Object(subresponse));
+            match result_row.get(navigation(&expansion.relationship)) {
+                Some(Value::Object(obj)) => {
+                    let subresponse = Response::interpret(obj, &expansion.query);
+                    prepared.insert(alias.0.clone(), Value::Object(subresponse));
+                }
+
+                // Collection-valued navigation properties expand to an array of entities.
+                Some(Value::Array(elements)) => {
+                    let subresponses = elements
+                        .iter()
+                        .filter_map(Value::as_object)
+                        .map(|element| {
+                            Value::Object(Response::interpret(element, &expansion.query))
+                        })
+                        .collect();
+
+                    prepared.insert(alias.0.clone(), Value::Array(subresponses));
+                }
+
+                _ => {}
             }
         }
 
         prepared
     }
+
+    /// The type of an entity. OData only annotates entities whose type isn't the one it expects,
+    /// and writes the annotation as `#Trippin.Employee`.
+    fn entity_type<'a>(
+        result_row: &'a Map<String, Value>,
+        query_structure: &'a super::Query,
+    ) -> Option<&'a str> {
+        match result_row.get(DISCRIMINATOR) {
+            Some(Value::String(annotation)) => Some(annotation.trim_start_matches('#')),
+            _ => query_structure.object_type.as_deref(),
+        }
+    }
+
+    /// Everything in an entity that its type doesn't declare, other than annotations (such as
+    /// `@odata.etag` or `Name@odata.type`) and the navigation properties we expanded. An entity
+    /// of a derived type declares the properties of that type, such as `Trippin.Employee/Cost`.
+    fn dynamic_properties(
+        result_row: &Map<String, Value>,
+        query_structure: &super::Query,
+    ) -> Map<String, Value> {
+        let declared = query_structure
+            .declared_properties
+            .as_ref()
+            .zip(Response::entity_type(result_row, query_structure))
+            .and_then(|(declared, entity_type)| declared.get(entity_type));
+
+        result_row
+            .iter()
+            .filter(|(key, _)| !key.contains('@'))
+            .filter(|(key, _)| !declared.is_some_and(|declared| declared.contains(*key)))
+            .filter(|(key, _)| {
+                !query_structure
+                    .fields
+                    .relationships
+                    .values()
+                    .any(|expansion| navigation(&expansion.relationship) == key.as_str())
+            })
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// A navigation property of a derived type is expanded through a type cast, such as
+/// `Trippin.Employee/Peers`, but the response calls it `Peers`.
+fn navigation(relationship: &str) -> &str {
+    match relationship.rsplit_once('/') {
+        Some((_, navigation)) => navigation,
+        None => relationship,
+    }
 }
@@ -0,0 +1,122 @@
+//! Services declare what each collection doesn't support with the Capabilities vocabulary. Rather
+//! than sending requests that the service will reject, we check queries against those
+//! restrictions up front, and we report what we find as unsupported operations.
+
+use metadata::ndc;
+use ndc_sdk::models;
+use std::collections::BTreeMap;
+
+/// Check that a query against the named collection (and any subquery through its relationships)
+/// only filters, sorts, and expands the way the service allows.
+pub fn check(
+    configuration: &ndc::Configuration,
+    relationships: &BTreeMap<String, models::Relationship>,
+    collection: &str,
+    query: &models::Query,
+) -> Result<(), String> {
+    let default = ndc::Restrictions::default();
+    let restrictions = match configuration.schema.collection(collection) {
+        Some(collection) => &collection.restrictions,
+        None => &default,
+    };
+
+    if let Some(predicate) = &query.predicate {
+        check_predicate(restrictions, collection, predicate)?;
+    }
+
+    if let Some(order_by) = &query.order_by {
+        for element in &order_by.elements {
+            if let models::OrderByTarget::Column { name, path } = &element.target {
+                if path.is_empty() && !restrictions.can_sort_by(name) {
+                    return Err(format!("{collection} can't be sorted by {name}."));
+                }
+            }
+        }
+    }
+
+    for field in query.fields.iter().flat_map(|fields| fields.values()) {
+        if let models::Field::Relationship {
+            relationship,
+            query,
+            ..
+        } = field
+        {
+            // A navigation property of a derived type is expanded through a type cast.
+            let navigation = match relationship.rsplit_once('/') {
+                Some((_, navigation)) => navigation,
+                None => relationship,
+            };
+
+            if !restrictions.can_expand(navigation) {
+                return Err(format!("{collection} can't expand {navigation}."));
+            }
+
+            if let Some(target) = relationships.get(relationship) {
+                check(
+                    configuration,
+                    relationships,
+                    &target.target_collection,
+                    query,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that a predicate over the named collection only filters by properties that the service
+/// allows. Mutations use this to check their predicates, too.
+pub fn check_predicate(
+    restrictions: &ndc::Restrictions,
+    collection: &str,
+    expression: &models::Expression,
+) -> Result<(), String> {
+    let mut columns = Vec::new();
+    columns_of(expression, &mut columns);
+
+    match columns
+        .into_iter()
+        .find(|column| !restrictions.can_filter_by(column))
+    {
+        Some(column) => Err(format!("{collection} can't be filtered by {column}.")),
+        None => Ok(()),
+    }
+}
+
+/// Collect the local columns that a predicate compares.
+fn columns_of<'a>(expression: &'a models::Expression, columns: &mut Vec<&'a String>) {
+    match expression {
+        models::Expression::And { expressions } | models::Expression::Or { expressions } => {
+            for expression in expressions {
+                columns_of(expression, columns);
+            }
+        }
+
+        models::Expression::Not { expression } => columns_of(expression, columns),
+
+        models::Expression::UnaryComparisonOperator { column, .. }
+        | models::Expression::BinaryArrayComparisonOperator { column, .. } => {
+            local_column(column, columns)
+        }
+
+        models::Expression::BinaryComparisonOperator { column, value, .. } => {
+            local_column(column, columns);
+
+            if let models::ComparisonValue::Column { column } = value {
+                local_column(column, columns);
+            }
+        }
+
+        models::Expression::Exists { .. } => {}
+    }
+}
+
+/// Columns of related collections and of the root collection are filtered in their own scope.
+fn local_column<'a>(target: &'a models::ComparisonTarget, columns: &mut Vec<&'a String>) {
+    if let models::ComparisonTarget::Column { name, path } = target {
+        if path.is_empty() {
+            columns.push(name);
+        }
+    }
+}
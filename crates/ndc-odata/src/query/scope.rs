@@ -0,0 +1,137 @@
+//! What we know about the rows that (part of) a query is looking at. OData literals are typed, so
+//! to write a filter, we need to know the type of each column we compare against: the same JSON
+//! string could be an `Edm.String`, an `Edm.Date`, or the member of an enum.
+
+use metadata::ndc;
+use ndc_sdk::models;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub struct Scope<'a> {
+    pub configuration: &'a ndc::Configuration,
+
+    /// The relationships of the request, which tell us where each subquery leads.
+    pub relationships: &'a BTreeMap<String, models::Relationship>,
+
+    /// The name of the object type of the rows, if we know it.
+    pub object_type: Option<String>,
+}
+
+impl<'a> Scope<'a> {
+    /// The scope of a query against a collection or function, or of a predicate over a
+    /// collection.
+    pub fn new(
+        configuration: &'a ndc::Configuration,
+        relationships: &'a BTreeMap<String, models::Relationship>,
+        collection: &str,
+    ) -> Self {
+        let object_type = match configuration.schema.collection(collection) {
+            Some(collection) => Some(collection.collection_type.to_string()),
+            None => configuration
+                .schema
+                .function(collection)
+                .map(|function| function.result_type.underlying_type().to_string()),
+        };
+
+        Scope {
+            configuration,
+            relationships,
+            object_type,
+        }
+    }
+
+    /// The scope of a subquery that follows the named relationship.
+    pub fn through(&self, relationship: &str) -> Self {
+        match self.relationships.get(relationship) {
+            Some(relationship) => Scope::new(
+                self.configuration,
+                self.relationships,
+                &relationship.target_collection,
+            ),
+
+            None => Scope {
+                configuration: self.configuration,
+                relationships: self.relationships,
+                object_type: None,
+            },
+        }
+    }
+
+    /// The object types that the rows might have: their own type, and the other types in its
+    /// inheritance hierarchy. We find these through the type casts of the collections whose rows
+    /// could be of the rows' type.
+    pub fn hierarchy(&self) -> BTreeSet<String> {
+        let object_type = match &self.object_type {
+            Some(object_type) => object_type,
+            None => return BTreeSet::new(),
+        };
+
+        let collections = &self.configuration.schema.collections;
+        let mut hierarchy = BTreeSet::from([object_type.clone()]);
+
+        for collection in collections {
+            let base_type = collection.collection_type.to_string();
+            let derived_types: BTreeSet<String> = collections
+                .iter()
+                .filter_map(|derived| derived.type_cast.as_ref())
+                .filter(|type_cast| type_cast.collection == collection.name)
+                .map(|type_cast| type_cast.entity_type.clone())
+                .collect();
+
+            if &base_type == object_type || derived_types.contains(object_type) {
+                hierarchy.insert(base_type);
+                hierarchy.extend(derived_types);
+            }
+        }
+
+        hierarchy
+    }
+
+    /// The field behind a column, if we know it.
+    pub fn field(&self, column: &str) -> Option<&'a ndc::ObjectField> {
+        // A column of a derived type is reached through a type cast: `Trippin.Employee/Cost`.
+        let (object_type, column) = match column.rsplit_once('/') {
+            Some((cast, column)) => (cast, column),
+            None => (self.object_type.as_deref()?, column),
+        };
+
+        let object_type = self.configuration.schema.object_types.get(object_type)?;
+        object_type.fields.get(column)
+    }
+
+    /// The name of the scalar type of a column, if we know it.
+    pub fn column_type(&self, column: &str) -> Option<String> {
+        self.field(column)
+            .map(|field| field.r#type.underlying_type().to_string())
+    }
+
+    /// If a column has an enum type, get its name and definition.
+    pub fn enum_type(&self, column: &str) -> Option<(String, &'a ndc::EnumType)> {
+        let column_type = self.column_type(column)?;
+        let enum_type = self.configuration.schema.enum_types.get(&column_type)?;
+        Some((column_type, enum_type))
+    }
+
+    /// Write a JSON value as an OData literal of the column's type. Without a type, we do the
+    /// best we can with the JSON value alone.
+    pub fn literal(&self, column: &str, value: &Value) -> Result<String, String> {
+        if let Some((name, enum_type)) = self.enum_type(column) {
+            return super::literals::encode_enum(&name, enum_type, value);
+        }
+
+        match self.column_type(column) {
+            Some(column_type) if ndc::geography::family(&column_type).is_some() => {
+                let srid = self
+                    .field(column)
+                    .and_then(|field| field.constraints.as_ref())
+                    .and_then(|constraints| constraints.srid);
+
+                super::literals::encode_spatial(value, &column_type, srid)
+                    .map_err(|reason| format!("Invalid value for {column}: {reason}"))
+            }
+            Some(column_type) => super::literals::encode_as(value, &column_type)
+                .map_err(|reason| format!("Invalid value for {column}: {reason}")),
+            None => Ok(super::literals::encode(value)),
+        }
+    }
+}
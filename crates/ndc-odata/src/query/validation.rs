@@ -0,0 +1,271 @@
+//! The metadata can constrain the values that a field or argument accepts, with facets such as
+//! `MaxLength` and with the Validation vocabulary. We check the values we're given against these
+//! constraints before we call the service, so that we can say exactly which field is at fault.
+
+use metadata::ndc;
+use ndc_sdk::models;
+use regex::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// Types whose precision is the number of digits of fractional seconds.
+const TEMPORAL_TYPES: [&str; 3] = ["Edm.DateTimeOffset", "Edm.Duration", "Edm.TimeOfDay"];
+
+/// Check the literals in a query's predicate (and those of any subquery through its
+/// relationships) against the constraints of the columns they're compared with.
+pub fn check(
+    configuration: &ndc::Configuration,
+    relationships: &BTreeMap<String, models::Relationship>,
+    collection: &str,
+    query: &models::Query,
+) -> Result<(), String> {
+    if let Some(predicate) = &query.predicate {
+        check_predicate(configuration, collection, predicate)?;
+    }
+
+    for field in query.fields.iter().flat_map(|fields| fields.values()) {
+        if let models::Field::Relationship {
+            relationship,
+            query,
+            ..
+        } = field
+        {
+            if let Some(target) = relationships.get(relationship) {
+                check(
+                    configuration,
+                    relationships,
+                    &target.target_collection,
+                    query,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the literals in a predicate over the named collection. Mutations use this to check their
+/// predicates, too.
+pub fn check_predicate(
+    configuration: &ndc::Configuration,
+    collection: &str,
+    expression: &models::Expression,
+) -> Result<(), String> {
+    let relationships = BTreeMap::new();
+    let scope = super::Scope::new(configuration, &relationships, collection);
+
+    check_expression(&scope, expression)
+}
+
+fn check_expression(scope: &super::Scope, expression: &models::Expression) -> Result<(), String> {
+    match expression {
+        models::Expression::And { expressions } | models::Expression::Or { expressions } => {
+            for expression in expressions {
+                check_expression(scope, expression)?;
+            }
+
+            Ok(())
+        }
+
+        models::Expression::Not { expression } => check_expression(scope, expression),
+
+        // Only equality compares a column with a value of the column's own type: the other
+        // operators take arguments of their own, such as a distance or a polygon.
+        models::Expression::BinaryComparisonOperator {
+            column: models::ComparisonTarget::Column { name, path },
+            operator: models::BinaryComparisonOperator::Equal,
+            value: models::ComparisonValue::Scalar { value },
+        } if path.is_empty() => match scope.field(name) {
+            Some(field) => check_value(
+                scope.configuration,
+                name,
+                &field.r#type,
+                field.constraints.as_ref(),
+                value,
+            ),
+            None => Ok(()),
+        },
+
+        _ => Ok(()),
+    }
+}
+
+/// Check a value of the given type, which we refer to by `path` if it is invalid. Objects are
+/// checked field by field, and arrays element by element. Nulls are left for the service to
+/// judge: the type already says whether they're allowed.
+pub fn check_value(
+    configuration: &ndc::Configuration,
+    path: &str,
+    r#type: &ndc::Type,
+    constraints: Option<&ndc::Constraints>,
+    value: &Value,
+) -> Result<(), String> {
+    match (r#type, value) {
+        (_, Value::Null) => Ok(()),
+
+        (ndc::Type::Nullable { underlying_type }, _) => {
+            check_value(configuration, path, underlying_type, constraints, value)
+        }
+
+        (ndc::Type::Collection { element_type }, Value::Array(elements)) => {
+            for (index, element) in elements.iter().enumerate() {
+                let path = format!("{path}[{index}]");
+                check_value(configuration, &path, element_type, constraints, element)?;
+            }
+
+            Ok(())
+        }
+
+        (ndc::Type::Qualified { qualified_type }, _) => {
+            let type_name = qualified_type.to_string();
+
+            if let Some(constraints) = constraints {
+                check_scalar(&type_name, constraints, value)
+                    .map_err(|reason| format!("Invalid value for {path}: {reason}."))?;
+            }
+
+            if let (Some(object_type), Value::Object(object)) =
+                (configuration.schema.object_types.get(&type_name), value)
+            {
+                for (name, field) in &object_type.fields {
+                    if let Some(value) = object.get(name) {
+                        let path = format!("{path}.{name}");
+                        let constraints = field.constraints.as_ref();
+                        check_value(configuration, &path, &field.r#type, constraints, value)?;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        _ => Ok(()),
+    }
+}
+
+/// Check a single value against its constraints, explaining what's wrong if it doesn't comply.
+fn check_scalar(
+    type_name: &str,
+    constraints: &ndc::Constraints,
+    value: &Value,
+) -> Result<(), String> {
+    let literal = match value {
+        Value::String(string) => string.clone(),
+        other => other.to_string(),
+    };
+
+    if let (Some(max_length), Value::String(string)) = (constraints.max_length, value) {
+        // Binary data is base64url-encoded, so four characters make three bytes.
+        let (length, unit) = match type_name {
+            "Edm.Binary" => (string.trim_end_matches('=').len() * 3 / 4, "bytes"),
+            _ => (string.chars().count(), "characters"),
+        };
+
+        if length > max_length {
+            return Err(format!("must be at most {max_length} {unit} long"));
+        }
+    }
+
+    if type_name == "Edm.Decimal" && !literal.contains(['e', 'E']) {
+        let unsigned = literal.trim_start_matches(['-', '+']);
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let digits = integer.trim_start_matches('0').len() + fraction.len();
+
+        if let Some(scale) = constraints.scale.filter(|scale| fraction.len() > *scale) {
+            return Err(format!("must have at most {scale} decimal places"));
+        }
+
+        if let Some(precision) = constraints
+            .precision
+            .filter(|precision| digits > *precision)
+        {
+            return Err(format!("must have at most {precision} digits"));
+        }
+    }
+
+    if TEMPORAL_TYPES.contains(&type_name) {
+        let fraction = match literal.split_once('.') {
+            Some((_, rest)) => rest.chars().take_while(char::is_ascii_digit).count(),
+            None => 0,
+        };
+
+        if let Some(precision) = constraints
+            .precision
+            .filter(|precision| fraction > *precision)
+        {
+            return Err(format!(
+                "must have at most {precision} digits of fractional seconds"
+            ));
+        }
+    }
+
+    if let Some(srid) = constraints.srid {
+        if let Some(given) = super::literals::spatial_reference(value).filter(|x| *x != srid) {
+            return Err(format!("must use SRID {srid}, not {given}"));
+        }
+    }
+
+    // A pattern that we can't compile is the service's to enforce, not ours.
+    if let (Some(pattern), Value::String(string)) = (&constraints.pattern, value) {
+        if let Some(regex) = compile(pattern) {
+            if !regex.is_match(string) {
+                return Err(format!("must match the pattern {pattern}"));
+            }
+        }
+    }
+
+    if let Some(minimum) = &constraints.minimum {
+        match compare(&literal, &minimum.value) {
+            Some(Ordering::Less) => return Err(format!("must be at least {}", minimum.value)),
+            Some(Ordering::Equal) if minimum.exclusive => {
+                return Err(format!("must be greater than {}", minimum.value))
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(maximum) = &constraints.maximum {
+        match compare(&literal, &maximum.value) {
+            Some(Ordering::Greater) => return Err(format!("must be at most {}", maximum.value)),
+            Some(Ordering::Equal) if maximum.exclusive => {
+                return Err(format!("must be less than {}", maximum.value))
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(allowed_values) = &constraints.allowed_values {
+        if !allowed_values.contains(&literal) {
+            return Err(format!("must be one of {}", allowed_values.join(", ")));
+        }
+    }
+
+    Ok(())
+}
+
+/// Compare a value with a bound. Numbers are compared numerically. Anything else is a date or time,
+/// and ISO 8601 values of the same form sort the same way as strings do.
+fn compare(value: &str, bound: &str) -> Option<Ordering> {
+    match (value.parse::<f64>(), bound.parse::<f64>()) {
+        (Ok(value), Ok(bound)) => value.partial_cmp(&bound),
+        (Err(_), Err(_)) => Some(value.cmp(bound)),
+        _ => None,
+    }
+}
+
+/// Compile a `Validation.Pattern`. Like an XML Schema pattern, it has to match the whole value, so
+/// we anchor it. The same few patterns are checked on every request, so we only compile each one
+/// once, and remember those that we can't compile, too.
+fn compile(pattern: &str) -> Option<Regex> {
+    static PATTERNS: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+
+    PATTERNS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(pattern.to_string())
+        .or_insert_with(|| Regex::new(&format!("^(?:{pattern})$")).ok())
+        .clone()
+}
@@ -28,7 +28,7 @@ pub fn get_schema(configuration: &ndc::Configuration) -> models::SchemaResponse
             .schema
             .scalar_types
             .iter()
-            .map(scalar_types::translate)
+            .map(|scalar_type| scalar_types::translate(&configuration.schema, scalar_type))
             .collect(),
 
         functions: configuration
@@ -9,44 +9,49 @@ pub fn translate(collections: &Vec<ndc::Collection>) -> Vec<models::CollectionIn
     for collection in collections {
         let mut foreign_keys = BTreeMap::new();
 
-        // Each collection has a key, so we can always generate a "foreign key" relationship to any
-        // entity for which we have a navigation property.
-        for (relationship_target, foreign_collection) in &collection.relationships {
-            let target_key = collections
-                .iter()
-                .find(|collection| &collection.name == foreign_collection)
-                .map(|collection| collection.key.clone())
-                .unwrap();
-
-            let column_mapping = BTreeMap::from([(relationship_target.clone(), target_key)]);
-
-            foreign_keys.insert(
-                relationship_target.clone(),
-                models::ForeignKeyConstraint {
-                    column_mapping,
-                    foreign_collection: foreign_collection.clone(),
-                },
-            );
+        // Navigation properties with referential constraints tell us exactly which columns relate
+        // the two collections.
+        for (relationship_target, column_mapping) in &collection.foreign_keys {
+            if let Some(foreign_collection) = collection.relationships.get(relationship_target) {
+                foreign_keys.insert(
+                    relationship_target.clone(),
+                    models::ForeignKeyConstraint {
+                        column_mapping: column_mapping.clone(),
+                        foreign_collection: foreign_collection.clone(),
+                    },
+                );
+            }
         }
 
         println!("{:?}", collection.collection_type.to_string());
 
-        // Using the collection's own key, we can also generate a unique key constraint.
-        let primary_key_constraint = format!("{}By{}", collection.name, collection.key.clone());
-        let uniqueness_constraint = models::UniquenessConstraint {
-            unique_columns: vec![collection.key.clone()],
-        };
+        // Using the collection's own key, we can also generate a unique key constraint. Parts of
+        // the key nested within complex properties aren't columns, so we can't constrain those.
+        let mut uniqueness_constraints = BTreeMap::new();
+
+        if collection.key.iter().all(|part| part.path.is_none()) {
+            let unique_columns: Vec<String> = collection
+                .key
+                .iter()
+                .map(|part| part.name.clone())
+                .collect();
+
+            uniqueness_constraints.insert(
+                format!("{}By{}", collection.name, unique_columns.join("And")),
+                models::UniquenessConstraint { unique_columns },
+            );
+        }
 
         results.push(models::CollectionInfo {
             name: collection.name.clone(),
             collection_type: collection.collection_type.to_string(),
             foreign_keys,
-            description: None,
-            arguments: BTreeMap::new(),
-            uniqueness_constraints: BTreeMap::from([(
-                primary_key_constraint,
-                uniqueness_constraint,
-            )]),
+            description: collection.description.clone(),
+            arguments: super::types::translate_arguments(
+                &collection.arguments,
+                &collection.argument_descriptions,
+            ),
+            uniqueness_constraints,
         });
     }
 
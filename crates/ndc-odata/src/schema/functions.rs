@@ -4,8 +4,11 @@ use ndc_sdk::models;
 pub fn translate(function: &ndc::Function) -> models::FunctionInfo {
     models::FunctionInfo {
         name: function.name.clone(),
-        arguments: super::types::translate_arguments(&function.arguments),
+        arguments: super::types::translate_arguments(
+            &function.arguments,
+            &function.argument_descriptions,
+        ),
         result_type: super::types::translate_type(&function.result_type),
-        description: None,
+        description: function.description.clone(),
     }
 }
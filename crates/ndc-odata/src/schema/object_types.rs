@@ -5,9 +5,9 @@ use std::collections::BTreeMap;
 pub fn translate((name, object_type): (&String, &ndc::ObjectType)) -> (String, models::ObjectType) {
     let mut fields = BTreeMap::new();
 
-    for (field_name, field_type) in &object_type.fields {
-        let r#type = super::types::translate_type(field_type);
-        let description = None;
+    for (field_name, field) in &object_type.fields {
+        let r#type = super::types::translate_type(&field.r#type);
+        let description = field.description.clone();
 
         fields.insert(
             field_name.clone(),
@@ -21,7 +21,7 @@ pub fn translate((name, object_type): (&String, &ndc::ObjectType)) -> (String, m
     (
         name.clone(),
         models::ObjectType {
-            description: None,
+            description: object_type.description.clone(),
             fields,
         },
     )
@@ -1,13 +1,54 @@
-use metadata::ndc::ScalarType;
+use metadata::ndc;
 use ndc_sdk::models;
 use std::collections::BTreeMap;
 
-pub fn translate(scalar_type: &ScalarType) -> (String, models::ScalarType) {
+pub fn translate(
+    schema: &ndc::Schema,
+    scalar_type: &ndc::ScalarType,
+) -> (String, models::ScalarType) {
+    let mut comparison_operators = BTreeMap::new();
+
+    // Flag enums can be tested for the presence of a member with OData's `has` operator.
+    if let Some(enum_type) = schema.enum_types.get(&scalar_type.0) {
+        if enum_type.is_flags {
+            comparison_operators.insert(
+                "has".to_string(),
+                models::ComparisonOperatorDefinition {
+                    argument_type: models::Type::Named {
+                        name: scalar_type.0.clone(),
+                    },
+                },
+            );
+        }
+    }
+
+    // Entities in a hierarchy can be tested for their type with OData's `isof` function.
+    if scalar_type.0 == ndc::derived_types::type_name_type().to_string() {
+        comparison_operators.insert(
+            "isof".to_string(),
+            models::ComparisonOperatorDefinition {
+                argument_type: models::Type::Named {
+                    name: scalar_type.0.clone(),
+                },
+            },
+        );
+    }
+
+    // Spatial types get operators backed by OData's `geo.` functions.
+    for (name, argument_type) in ndc::geography::comparison_operators(&scalar_type.0) {
+        comparison_operators.insert(
+            name,
+            models::ComparisonOperatorDefinition {
+                argument_type: super::types::translate_type(&argument_type),
+            },
+        );
+    }
+
     (
         scalar_type.0.clone(),
         models::ScalarType {
             aggregate_functions: BTreeMap::new(),
-            comparison_operators: BTreeMap::new(),
+            comparison_operators,
         },
     )
 }
@@ -19,11 +19,11 @@ pub fn translate_type(r#type: &ndc::Type) -> models::Type {
     }
 }
 
-/// Translate a set of `ndc-odata` arguments to `ndc-spec` arguments. OData doesn't provide a
-/// description for arguments to its functions or procedures, so this function really just adds
-/// empty descriptions to each argument.
+/// Translate a set of `ndc-odata` arguments to `ndc-spec` arguments, along with the descriptions
+/// of any parameters that the service documents.
 pub fn translate_arguments(
     arguments: &BTreeMap<String, ndc::Type>,
+    descriptions: &BTreeMap<String, String>,
 ) -> BTreeMap<String, models::ArgumentInfo> {
     let mut results = BTreeMap::new();
 
@@ -32,7 +32,7 @@ pub fn translate_arguments(
             argument_name.clone(),
             models::ArgumentInfo {
                 argument_type: translate_type(argument_type),
-                description: None,
+                description: descriptions.get(argument_name).cloned(),
             },
         );
     }
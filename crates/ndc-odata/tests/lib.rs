@@ -14,7 +14,7 @@ enum Method {
     POST,
 }
 
-#[test_each::path(glob = "crates/ndc-odata/tests/**/*.json")]
+#[test_each::path(glob = "crates/ndc-odata/tests/goldenfiles/**/*.json")]
 fn test_snapshot(path: path::PathBuf) {
     let client = reqwest::blocking::Client::new();
     let content = std::fs::read_to_string(&path).unwrap();